@@ -0,0 +1,121 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use crossterm::event::{KeyCode, KeyModifiers};
+use noted::{config::Syntax, highlight, textbox::TextBox};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+// a short note and a pasted log of about 4MB, edits should cost the same
+const SIZES: [usize; 2] = [100, 50_000];
+
+fn note(lines: usize) -> Vec<String> {
+    (0..lines)
+        .map(|i| {
+            format!(
+                "{i:>6} INFO request handled in {}ms, status 200, path /api/notes/{i}",
+                i % 97
+            )
+        })
+        .collect()
+}
+
+// the same size of note as one long fenced block of Rust
+fn code(lines: usize) -> Vec<String> {
+    let mut code = vec!["```rust".to_string()];
+    code.extend(
+        (1..lines).map(|i| format!("    let path_{i} = format!(\"/api/notes/{{}}\", {i}); // {i}")),
+    );
+    code
+}
+
+// an editor at the middle of the note, laid out for an 80x24 terminal
+fn editor(text: &[String]) -> TextBox {
+    let mut textbox = TextBox::new(24);
+    textbox.load(text.to_vec());
+    textbox.set_viewport(78, 22);
+    textbox.move_cursor_to(text.len() / 2, 10);
+    textbox
+}
+
+fn typing(c: &mut Criterion) {
+    let area = Rect::new(0, 0, 80, 24);
+    let mut group = c.benchmark_group("editor");
+
+    for lines in SIZES {
+        let text = note(lines);
+
+        group.bench_with_input(
+            BenchmarkId::new("type and redraw", lines),
+            &text,
+            |b, text| {
+                b.iter_batched_ref(
+                    || (editor(text), Buffer::empty(area)),
+                    |(textbox, buf)| {
+                        textbox.handle_input(KeyCode::Char('x'), KeyModifiers::NONE);
+                        textbox.render(area, buf);
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("type a line", lines), &text, |b, text| {
+            b.iter_batched_ref(
+                || editor(text),
+                |textbox| {
+                    for ch in "the quick brown fox jumps over the lazy dog".chars() {
+                        textbox.handle_input(KeyCode::Char(ch), KeyModifiers::NONE);
+                    }
+                    textbox.handle_input(KeyCode::Enter, KeyModifiers::NONE);
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("type in code and redraw", lines),
+            &code(lines),
+            |b, text| {
+                b.iter_batched_ref(
+                    || {
+                        let mut textbox = editor(text);
+                        textbox.set_highlighter(highlight::for_syntax(Syntax::Markdown));
+                        textbox.render(area, &mut Buffer::empty(area));
+                        (textbox, Buffer::empty(area))
+                    },
+                    |(textbox, buf)| {
+                        textbox.handle_input(KeyCode::Char('x'), KeyModifiers::NONE);
+                        textbox.render(area, buf);
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("move and redraw", lines),
+            &text,
+            |b, text| {
+                b.iter_batched_ref(
+                    || (editor(text), Buffer::empty(area)),
+                    |(textbox, buf)| {
+                        textbox.handle_input(KeyCode::Down, KeyModifiers::NONE);
+                        textbox.render(area, buf);
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        let pasted = text.join("\n");
+        group.bench_with_input(BenchmarkId::new("paste", lines), &pasted, |b, pasted| {
+            b.iter_batched_ref(
+                || editor(&text),
+                |textbox| textbox.insert_str(pasted),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, typing);
+criterion_main!(benches);
@@ -1,41 +1,209 @@
 use crate::{
-    note::{Note, NoteList},
+    clipboard,
+    config::Config,
+    fuzzy::{self, FuzzyMatch},
+    highlight,
+    keybindings::{Chord, Keybindings},
+    note::{LoadError, Note, NoteId, NoteList},
+    notebook::{self, TreeRow},
+    preview::Preview,
+    search::{self, SearchMatch, SearchOptions},
+    storage,
+    tags::TagFilter,
+    tasks::{self, Task},
     textbox::TextBox,
 };
 use anyhow::Result;
+use chrono::{Duration, Utc};
+use regex::Regex;
+use std::{collections::HashSet, path::PathBuf};
 // use crossterm::event::KeyCode;
 
+// how many recently opened notes are remembered for the quick-open ranking
+const RECENT_LIMIT: usize = 20;
+// added to the match score per place a note is away from the end of `recent`
+const RECENT_BONUS: i64 = 4;
+
 #[derive(Debug)]
 pub enum CurrentView {
     Main,
     Editing,
+    Trash,
+    Search,
+    Tasks,
+}
+
+// single line input shown in the nav bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    Title,
+    Tags,
+    TagFilter,
+    NewNotebook,
+    RenameNotebook,
+    MoveNotebook,
+    MoveNote,
+    Search,
+}
+
+impl Prompt {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Prompt::Title => "Enter title",
+            Prompt::Tags => "Enter tags",
+            Prompt::TagFilter => "Filter tags",
+            Prompt::NewNotebook => "New notebook",
+            Prompt::RenameNotebook => "Rename notebook",
+            Prompt::MoveNotebook => "Move notebook to",
+            Prompt::MoveNote => "Move note to notebook",
+            Prompt::Search => "Search",
+        }
+    }
+}
+
+// actions that can't be undone ask for a y/n first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirm {
+    Purge(NoteId),
+    EmptyTrash,
+}
+
+// Ctrl-P overlay for jumping straight to a note by its title
+#[derive(Debug, Default)]
+pub struct QuickOpen {
+    pub query: String,
+    pub selected: usize,
+}
+
+// Ctrl-F bar for finding, and replacing, text in the open note
+#[derive(Debug, Default)]
+pub struct FindBar {
+    pub query: String,
+    pub replacement: String,
+    // typing goes to the replacement instead of the query
+    pub replacing: bool,
+    pub options: SearchOptions,
+    // the cursor when the bar opened, the query is searched from here
+    pub origin: (usize, usize),
+    // None while the query is empty or isn't a valid regex
+    pub regex: Option<Regex>,
+    pub matches: Vec<((usize, usize), (usize, usize))>,
+    pub invalid: bool,
+}
+
+impl FindBar {
+    // which of the matches the cursor is on
+    pub fn current(&self, cursor: (usize, usize)) -> Option<usize> {
+        self.matches.iter().position(|&(start, _)| start == cursor)
+    }
+}
+
+// the open note's Markdown rendered next to the editor, scrolled along with it
+#[derive(Debug, Default)]
+pub struct PreviewPane {
+    pub preview: Preview,
+    // first line shown
+    pub scroll: usize,
+    height: usize,
+    // the editor's top row when the preview was last scrolled to match it
+    synced: Option<usize>,
+    // the text revision and width the preview was rendered for
+    rendered: Option<(usize, usize)>,
+}
+
+// shown instead of the notes when the database exists but couldn't be loaded
+#[derive(Debug)]
+pub struct Recovery {
+    pub error: LoadError,
+    pub backups: Vec<PathBuf>,
+    pub show_details: bool,
 }
+
 #[derive(Debug)]
 pub struct AppState {
     pub should_quit: bool,
     pub notes: NoteList,
-    pub current_note: usize,
+    pub current_note: Option<NoteId>,
     pub current_view: CurrentView,
-    pub save_file: String,
+    pub save_file: PathBuf,
+    pub config: Config,
     pub textbox: TextBox,
-    pub editing_title: bool,
-    pub title_buf: String,
+    pub prompt: Option<Prompt>,
+    pub prompt_buf: String,
+    pub tag_filter: Option<TagFilter>,
+    pub sidebar_focus: bool,
+    // None shows the notes of every notebook
+    pub current_notebook: Option<String>,
+    pub collapsed: HashSet<String>,
     pub terminal_height: usize,
+    pub current_trashed: Option<NoteId>,
+    pub confirm: Option<Confirm>,
+    pub recovery: Option<Recovery>,
+    pub read_only: bool,
+    // one-off message shown in the nav bar until the next key press
+    pub status: Option<String>,
+    pub search_query: String,
+    pub search_options: SearchOptions,
+    pub search_results: Vec<SearchMatch>,
+    pub search_selected: usize,
+    pub tasks: Vec<Task>,
+    pub task_selected: usize,
+    pub quick_open: Option<QuickOpen>,
+    pub find: Option<FindBar>,
+    pub preview: Option<PreviewPane>,
+    // notes opened in the editor, most recent first
+    pub recent: Vec<NoteId>,
+    // copied text waiting to be sent to the system clipboard
+    pub clipboard: Option<String>,
+    // the register revision last sent to or read from the system clipboard
+    clipboard_revision: usize,
+    pub keybindings: Keybindings,
+    // keys of a sequence that isn't complete yet, e.g. the first `g` of `g g`
+    pub pending_keys: Vec<Chord>,
 }
 
 impl AppState {
     // construct new AppState
-    pub fn new(terminal_height: usize) -> Self {
+    pub fn new(terminal_height: usize, save_file: PathBuf, config: Config) -> Self {
+        let mut textbox = TextBox::new(terminal_height);
+        textbox.set_editing_mode(config.editing_mode);
+        textbox.set_wrap_mode(config.wrap_mode);
+        textbox.set_vertical_movement(config.vertical_movement);
+        textbox.set_highlighter(highlight::for_syntax(config.syntax));
         AppState {
             should_quit: false,
             notes: NoteList::new(),
-            current_note: 0,
+            current_note: None,
             current_view: CurrentView::Main,
-            save_file: String::from("./notes/test.json"),
-            textbox: TextBox::new(terminal_height),
-            editing_title: false,
-            title_buf: String::new(),
-            terminal_height: terminal_height,
+            save_file,
+            config,
+            textbox,
+            prompt: None,
+            prompt_buf: String::new(),
+            tag_filter: None,
+            sidebar_focus: false,
+            current_notebook: None,
+            collapsed: HashSet::new(),
+            terminal_height,
+            current_trashed: None,
+            confirm: None,
+            recovery: None,
+            read_only: false,
+            status: None,
+            search_query: String::new(),
+            search_options: SearchOptions::default(),
+            search_results: Vec::new(),
+            search_selected: 0,
+            tasks: Vec::new(),
+            task_selected: 0,
+            quick_open: None,
+            find: None,
+            preview: None,
+            recent: Vec::new(),
+            clipboard: None,
+            clipboard_revision: 0,
+            keybindings: Keybindings::default(),
+            pending_keys: Vec::new(),
         }
     }
 
@@ -57,13 +225,590 @@ impl AppState {
     }
 
     pub fn save(&self) -> Result<()> {
-        self.notes.save(self.save_file.as_str())
+        if self.read_only {
+            anyhow::bail!("Notes database couldn't be loaded, refusing to overwrite it");
+        }
+        self.notes
+            .save_with_backups(&self.save_file, self.config.backups)
+    }
+
+    // save, showing the error in the nav bar if it failed. Returns whether
+    // the notes were saved
+    pub fn try_save(&mut self) -> bool {
+        match self.save() {
+            Ok(()) => true,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    // store the editor's text in its note and save it before leaving the
+    // editor. Returns whether the editor can be left, which it can't when the
+    // save failed. After starting empty there is nothing to save
+    pub fn save_editor(&mut self) -> bool {
+        self.set_current_note();
+        self.read_only || self.try_save()
     }
 
     pub fn set_current_note(&mut self) {
-        match self.notes.get(self.current_note) {
-            Some(note) => note.set_content(self.textbox.text.clone()),
-            None => {}
+        if let Some(note) = self.current_note.and_then(|id| self.notes.get(id)) {
+            // an empty editor is an empty note rather than one empty line
+            let content = match self.textbox.text.is_empty() {
+                true => Vec::new(),
+                false => self.textbox.text.to_lines(),
+            };
+            note.set_content(content)
+        }
+    }
+
+    // load the selected note into the editor
+    pub fn open_current_note(&mut self) {
+        let content = match self.current_note.and_then(|id| self.notes.find(id)) {
+            Some(note) => note.content.clone(),
+            None => Vec::new(),
+        };
+        self.textbox.load(content);
+        if let Some(id) = self.current_note {
+            self.recent.retain(|&recent| recent != id);
+            self.recent.insert(0, id);
+            self.recent.truncate(RECENT_LIMIT);
+        }
+        self.current_view = CurrentView::Editing
+    }
+
+    // notes whose title matches the quick-open query, best match first
+    pub fn quick_open_results(&self) -> Vec<(NoteId, FuzzyMatch)> {
+        let query = match &self.quick_open {
+            Some(quick_open) => quick_open.query.as_str(),
+            None => return Vec::new(),
+        };
+
+        let mut results: Vec<(NoteId, FuzzyMatch)> = self
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let mut found = fuzzy::fuzzy_match(query, &note.title)?;
+                if let Some(idx) = self.recent.iter().position(|&id| id == note.id) {
+                    found.score += (RECENT_LIMIT - idx) as i64 * RECENT_BONUS;
+                }
+                Some((note.id, found))
+            })
+            .collect();
+        results.sort_by_key(|(_, found)| std::cmp::Reverse(found.score));
+        results
+    }
+
+    // open the selected quick-open result, saving the note being edited first
+    pub fn open_quick_open_selection(&mut self) {
+        let selected = self.quick_open.as_ref().map_or(0, |q| q.selected);
+        let Some((id, _)) = self.quick_open_results().get(selected).cloned() else {
+            return;
+        };
+        self.quick_open = None;
+
+        let saved = match self.current_view {
+            CurrentView::Editing => self.save_editor(),
+            _ => true,
+        };
+        if saved {
+            self.current_note = Some(id);
+            self.open_current_note();
+        }
+    }
+
+    // send what the editor copied or cut since the last call to the system
+    // clipboard
+    pub fn sync_clipboard(&mut self) {
+        let revision = self.textbox.register_revision();
+        if revision == self.clipboard_revision {
+            return;
+        }
+        self.clipboard_revision = revision;
+        if self.config.osc52 || self.config.clipboard_tools {
+            self.clipboard = Some(self.textbox.register.clone());
+        }
+    }
+
+    // paste from the system clipboard through a clipboard tool when they are
+    // turned on. With OSC 52 the register holds what was last copied anyway,
+    // and text copied elsewhere arrives as a terminal paste, so the tools are
+    // only asked when OSC 52 is off or nothing was copied yet
+    pub fn paste_from_clipboard(&mut self) {
+        let ask_tools =
+            self.config.clipboard_tools && (!self.config.osc52 || self.textbox.register.is_empty());
+        if !ask_tools {
+            return;
+        }
+        if let Some(text) = clipboard::paste_with_tools().filter(|text| !text.is_empty()) {
+            self.textbox.set_register(text, false);
+            // it came from the clipboard, there is no need to send it back
+            self.clipboard_revision = self.textbox.register_revision();
+        }
+    }
+
+    pub fn open_prompt(&mut self, prompt: Prompt, initial: String) {
+        self.prompt = Some(prompt);
+        self.prompt_buf = initial;
+    }
+
+    pub fn close_prompt(&mut self) {
+        self.prompt = None;
+        self.prompt_buf.clear();
+    }
+
+    pub fn open_find(&mut self) {
+        let cursor = &self.textbox.cursor;
+        self.find = Some(FindBar {
+            origin: (cursor.row, cursor.col),
+            ..Default::default()
+        });
+    }
+
+    pub fn close_find(&mut self) {
+        self.find = None;
+        self.textbox.set_highlight(None);
+    }
+
+    // compile the query after it or an option changed, and move to the first
+    // match from where the search started
+    pub fn run_find(&mut self) {
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        let compiled = match find.query.is_empty() {
+            true => Ok(None),
+            false => find.options.compile(&find.query).map(Some),
+        };
+        find.invalid = compiled.is_err();
+        find.regex = compiled.ok().flatten();
+        self.textbox.set_highlight(find.regex.clone());
+
+        let found = match &find.regex {
+            Some(regex) => self.textbox.find_forward(regex, find.origin),
+            None => false,
+        };
+        if !found {
+            self.textbox.move_cursor_to(find.origin.0, find.origin.1);
+        }
+        self.refresh_find();
+    }
+
+    fn refresh_find(&mut self) {
+        if let Some(find) = self.find.as_mut() {
+            find.matches = match &find.regex {
+                Some(regex) => self.textbox.find_all(regex),
+                None => Vec::new(),
+            };
+        }
+    }
+
+    pub fn find_next(&mut self, forward: bool) {
+        let Some(regex) = self.find.as_ref().and_then(|find| find.regex.clone()) else {
+            return;
+        };
+        let (row, col) = (self.textbox.cursor.row, self.textbox.cursor.col);
+        match forward {
+            true => self.textbox.find_forward(&regex, (row, col + 1)),
+            false => self.textbox.find_backward(&regex, (row, col)),
+        };
+    }
+
+    // replace the match at the cursor, or just move to the next one if the
+    // cursor isn't on a match. Capture groups are filled in for regex queries
+    pub fn replace_next(&mut self) {
+        let Some(find) = self.find.as_ref() else {
+            return;
+        };
+        let Some(regex) = find.regex.clone() else {
+            return;
+        };
+        let (replacement, expand) = (find.replacement.clone(), find.options.regex);
+        if !self.textbox.replace_next(&regex, &replacement, expand) {
+            self.find_next(true);
+        }
+        self.refresh_find();
+    }
+
+    pub fn replace_all(&mut self) {
+        let Some(find) = self.find.as_ref() else {
+            return;
+        };
+        let Some(regex) = find.regex.clone() else {
+            return;
+        };
+        let (replacement, expand) = (find.replacement.clone(), find.options.regex);
+        let count = self.textbox.replace_all(&regex, &replacement, expand);
+        self.status = Some(format!("Replaced {} matches", count));
+        self.refresh_find();
+    }
+
+    pub fn toggle_preview(&mut self) {
+        self.preview = match self.preview {
+            Some(_) => None,
+            None => Some(PreviewPane::default()),
+        };
+    }
+
+    // render the preview again when the note changed or the pane was resized,
+    // and scroll it to the editor's top row when the editor has scrolled
+    pub fn sync_preview(&mut self, width: usize, height: usize) {
+        let Some(pane) = self.preview.as_mut() else {
+            return;
+        };
+        let rendered = Some((self.textbox.revision(), width));
+        if pane.rendered != rendered {
+            pane.preview.update(&self.textbox.text.to_string(), width);
+            pane.rendered = rendered;
+            pane.synced = None;
+        }
+        let top = self.textbox.top_row();
+        if pane.synced != Some(top) {
+            pane.scroll = pane.preview.line_at(top);
+            pane.synced = Some(top);
+        }
+        pane.height = height;
+        pane.scroll = pane
+            .scroll
+            .min(pane.preview.lines.len().saturating_sub(height));
+    }
+
+    // scroll the preview a line, and the editor to the row now at its top
+    pub fn scroll_preview(&mut self, down: bool) {
+        let Some(pane) = self.preview.as_mut() else {
+            return;
+        };
+        let before = pane.preview.row_at(pane.scroll);
+        pane.scroll = match down {
+            true => (pane.scroll + 1).min(pane.preview.lines.len().saturating_sub(pane.height)),
+            false => pane.scroll.saturating_sub(1),
+        };
+        let row = pane.preview.row_at(pane.scroll);
+        if row != before {
+            self.textbox.scroll_to(row);
+            pane.synced = Some(self.textbox.top_row());
+        }
+    }
+
+    // ids of the notes shown in the table, in display order
+    pub fn visible_notes(&self) -> Vec<NoteId> {
+        self.notes
+            .iter()
+            .filter(|note| match &self.current_notebook {
+                Some(path) => notebook::is_within(&note.notebook, path),
+                None => true,
+            })
+            .filter(|note| match &self.tag_filter {
+                Some(filter) => filter.matches(note),
+                None => true,
+            })
+            .map(|note| note.id)
+            .collect()
+    }
+
+    // search all notes for `search_query` and show the results
+    pub fn run_search(&mut self) -> Result<()> {
+        self.search_results = search::search(&self.notes, &self.search_query, self.search_options)?;
+        self.search_selected = 0;
+        self.current_view = CurrentView::Search;
+        Ok(())
+    }
+
+    // open the note of the selected result with the cursor on the match
+    pub fn open_search_result(&mut self) {
+        let Some(result) = self.search_results.get(self.search_selected).cloned() else {
+            return;
+        };
+        self.current_note = Some(result.note);
+        self.open_current_note();
+        match result.line {
+            Some(row) => self.textbox.move_cursor_to(row, result.start),
+            None => self.textbox.move_cursor_to(0, 0),
+        }
+    }
+
+    // collect the open tasks of every note and show them
+    pub fn open_tasks(&mut self) {
+        self.tasks = tasks::open_tasks(&self.notes);
+        self.task_selected = 0;
+        self.current_view = CurrentView::Tasks;
+    }
+
+    // open the note of the selected task with the cursor on its line
+    pub fn open_task(&mut self) {
+        let Some(task) = self.tasks.get(self.task_selected).cloned() else {
+            return;
+        };
+        self.current_note = Some(task.note);
+        self.open_current_note();
+        self.textbox.move_cursor_to(task.line, 0);
+    }
+
+    // check off the selected task in its note and save, the task leaves the list
+    pub fn complete_task(&mut self) -> Result<()> {
+        let Some(task) = self.tasks.get(self.task_selected) else {
+            return Ok(());
+        };
+        if let Some(note) = self.notes.get(task.note) {
+            let mut content = note.content.clone();
+            if let Some(done) = content
+                .get(task.line)
+                .and_then(|line| tasks::complete(line))
+            {
+                content[task.line] = done;
+                note.set_content(content);
+            }
+        }
+        self.tasks = tasks::open_tasks(&self.notes);
+        self.task_selected = self.task_selected.min(self.tasks.len().saturating_sub(1));
+        self.save()
+    }
+
+    // position of the selected note in the table
+    pub fn current_index(&self) -> Option<usize> {
+        let id = self.current_note?;
+        self.visible_notes()
+            .iter()
+            .position(|&visible| visible == id)
+    }
+
+    pub fn select_next(&mut self) {
+        let visible = self.visible_notes();
+        let next = match self.current_index() {
+            Some(idx) => (idx + 1).min(visible.len().saturating_sub(1)),
+            None => 0,
+        };
+        self.current_note = visible.get(next).copied();
+    }
+
+    pub fn select_prev(&mut self) {
+        let visible = self.visible_notes();
+        let prev = self.current_index().unwrap_or(0).saturating_sub(1);
+        self.current_note = visible.get(prev).copied();
+    }
+
+    pub fn select_first(&mut self) {
+        self.current_note = self.visible_notes().first().copied();
+    }
+
+    pub fn select_last(&mut self) {
+        self.current_note = self.visible_notes().last().copied();
+    }
+
+    // move the selection to the first visible note if it was filtered out
+    pub fn ensure_visible_selection(&mut self) {
+        if self.current_index().is_none() {
+            self.current_note = self.visible_notes().first().copied();
+        }
+    }
+
+    // remove the selected note, keeping the selection on the same row
+    pub fn trash_current_note(&mut self) -> bool {
+        let (Some(id), Some(idx)) = (self.current_note, self.current_index()) else {
+            return false;
+        };
+        let trashed = self.notes.trash(id);
+        let visible = self.visible_notes();
+        self.current_note = visible
+            .get(idx.min(visible.len().saturating_sub(1)))
+            .copied();
+        trashed
+    }
+
+    // purge notes that have been in the trash longer than the configured retention
+    pub fn purge_expired_trash(&mut self) -> usize {
+        match self.config.trash_retention_days {
+            0 => 0,
+            days => self
+                .notes
+                .purge_expired(Duration::days(days.into()), Utc::now()),
         }
     }
+
+    // ids of trashed notes, most recently deleted first
+    pub fn trashed_notes(&self) -> Vec<NoteId> {
+        self.notes.trashed().rev().map(|note| note.id).collect()
+    }
+
+    pub fn trashed_index(&self) -> Option<usize> {
+        let id = self.current_trashed?;
+        self.trashed_notes()
+            .iter()
+            .position(|&trashed| trashed == id)
+    }
+
+    pub fn open_trash(&mut self) {
+        if self.purge_expired_trash() > 0 {
+            self.try_save();
+        }
+        self.current_trashed = self.trashed_notes().first().copied();
+        self.current_view = CurrentView::Trash
+    }
+
+    pub fn select_next_trashed(&mut self) {
+        let trashed = self.trashed_notes();
+        let next = match self.trashed_index() {
+            Some(idx) => (idx + 1).min(trashed.len().saturating_sub(1)),
+            None => 0,
+        };
+        self.current_trashed = trashed.get(next).copied();
+    }
+
+    pub fn select_prev_trashed(&mut self) {
+        let trashed = self.trashed_notes();
+        let prev = self.trashed_index().unwrap_or(0).saturating_sub(1);
+        self.current_trashed = trashed.get(prev).copied();
+    }
+
+    pub fn restore_current_trashed(&mut self) -> bool {
+        let (Some(id), Some(idx)) = (self.current_trashed, self.trashed_index()) else {
+            return false;
+        };
+        let restored = self.notes.restore(id);
+        self.select_trashed_row(idx);
+        restored
+    }
+
+    pub fn purge_trashed(&mut self, id: NoteId) -> Option<Note> {
+        let idx = self.trashed_index().unwrap_or(0);
+        let purged = self.notes.purge(id);
+        self.select_trashed_row(idx);
+        purged
+    }
+
+    // keep the selection on the same row after a note left the trash
+    fn select_trashed_row(&mut self, idx: usize) {
+        let trashed = self.trashed_notes();
+        self.current_trashed = trashed
+            .get(idx.min(trashed.len().saturating_sub(1)))
+            .copied();
+    }
+
+    pub fn set_tag_filter(&mut self, filter: Option<TagFilter>) {
+        self.tag_filter = filter;
+        self.ensure_visible_selection();
+    }
+
+    // notebook new notes are created in
+    pub fn target_notebook(&self) -> String {
+        self.current_notebook
+            .clone()
+            .unwrap_or_else(|| notebook::DEFAULT_NOTEBOOK.to_string())
+    }
+
+    pub fn tree_rows(&self) -> Vec<TreeRow> {
+        notebook::tree_rows(self.notes.notebooks(), &self.collapsed)
+    }
+
+    // position of the selected notebook in the sidebar
+    pub fn tree_index(&self) -> usize {
+        self.tree_rows()
+            .iter()
+            .position(|row| row.path == self.current_notebook)
+            .unwrap_or(0)
+    }
+
+    pub fn select_notebook(&mut self, path: Option<String>) {
+        self.current_notebook = path;
+        self.ensure_visible_selection();
+    }
+
+    pub fn select_next_notebook(&mut self) {
+        let rows = self.tree_rows();
+        let next = (self.tree_index() + 1).min(rows.len() - 1);
+        self.select_notebook(rows[next].path.clone());
+    }
+
+    pub fn select_prev_notebook(&mut self) {
+        let rows = self.tree_rows();
+        let prev = self.tree_index().saturating_sub(1);
+        self.select_notebook(rows[prev].path.clone());
+    }
+
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        if let Some(path) = self.current_notebook.clone() {
+            if collapsed {
+                self.collapsed.insert(path);
+            } else {
+                self.collapsed.remove(&path);
+            }
+        }
+    }
+
+    pub fn toggle_collapsed(&mut self) {
+        if let Some(path) = &self.current_notebook {
+            let collapsed = self.collapsed.contains(path);
+            self.set_collapsed(!collapsed);
+        }
+    }
+
+    // rename or move the selected notebook, keeping it selected
+    pub fn move_current_notebook(&mut self, to: &str) -> Result<()> {
+        let Some(from) = self.current_notebook.clone() else {
+            anyhow::bail!("Select a notebook first");
+        };
+        self.notes.move_notebook(&from, to)?;
+        self.collapsed = self
+            .collapsed
+            .iter()
+            .map(|path| match notebook::is_within(path, &from) {
+                true => notebook::rebase(path, &from, to),
+                false => path.clone(),
+            })
+            .collect();
+        self.select_notebook(Some(to.to_string()));
+        Ok(())
+    }
+
+    // load the notes database, falling back to the recovery dialog if it is broken
+    pub fn load_notes(&mut self) {
+        match NoteList::load(&self.save_file) {
+            Ok(notes) => {
+                self.notes = notes;
+                self.current_note = self.notes.id_at(0);
+                // persist migrations of an older file and expired trash
+                let purged = self.purge_expired_trash();
+                if self.notes.was_migrated() || purged > 0 {
+                    self.try_save();
+                }
+            }
+            Err(e) if e.is_not_found() => self.notes = NoteList::new(),
+            Err(e) => {
+                self.recovery = Some(Recovery {
+                    error: e,
+                    backups: storage::existing_backups(&self.save_file),
+                    show_details: false,
+                })
+            }
+        }
+    }
+
+    // try the newest backup, moving on to older ones if it is broken too
+    pub fn restore_backup(&mut self) {
+        let Some(recovery) = self.recovery.as_mut() else {
+            return;
+        };
+        if recovery.backups.is_empty() {
+            return;
+        }
+
+        let backup = recovery.backups.remove(0);
+        match NoteList::load(&backup) {
+            Ok(notes) => {
+                self.notes = notes;
+                self.current_note = self.notes.id_at(0);
+                self.recovery = None;
+            }
+            Err(e) => recovery.error = e,
+        }
+    }
+
+    // start with no notes and never save over the broken database
+    pub fn start_empty(&mut self) {
+        self.notes = NoteList::new();
+        self.current_note = None;
+        self.read_only = true;
+        self.recovery = None;
+    }
 }
@@ -0,0 +1,145 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    fmt,
+    io::{Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+// tools tried in order when the system clipboard is reached through a command
+const COPY_TOOLS: &[(&str, &[&str])] = &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])];
+const PASTE_TOOLS: &[(&str, &[&str])] = &[
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-o"]),
+];
+
+// how long a clipboard tool gets before it is killed, the editor waits on it
+const TOOL_TIMEOUT: Duration = Duration::from_millis(500);
+
+// OSC 52 escape sequence asking the terminal to put text on the clipboard,
+// this works over SSH as long as the terminal allows it
+pub struct SetClipboard<'a>(pub &'a str);
+
+impl crossterm::Command for SetClipboard<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "\x1b]52;c;{}\x07", STANDARD.encode(self.0))
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "OSC 52 needs an ANSI terminal",
+        ))
+    }
+
+    #[cfg(windows)]
+    fn is_ansi_code_supported(&self) -> bool {
+        true
+    }
+}
+
+// wait for a tool to exit, killing it once it runs past the timeout
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Option<ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Some(status),
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+}
+
+// pipe the text into the first clipboard tool that is installed and works.
+// The text is written on another thread so a tool that stops reading can't
+// block the editor
+pub fn copy_with_tools(text: &str) -> bool {
+    COPY_TOOLS.iter().any(|(cmd, args)| {
+        let Ok(mut child) = Command::new(cmd)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let Some(mut stdin) = child.stdin.take() else {
+            return false;
+        };
+        let (send, written) = mpsc::channel();
+        let text = text.to_string();
+        thread::spawn(move || send.send(stdin.write_all(text.as_bytes()).is_ok()));
+
+        let written = written.recv_timeout(TOOL_TIMEOUT).unwrap_or(false);
+        let timeout = if written {
+            TOOL_TIMEOUT
+        } else {
+            Duration::ZERO
+        };
+        wait_timeout(&mut child, timeout).is_some_and(|status| status.success()) && written
+    })
+}
+
+// read the clipboard through the first tool that is installed and works, its
+// output is read on another thread for the same reason
+pub fn paste_with_tools() -> Option<String> {
+    PASTE_TOOLS.iter().find_map(|(cmd, args)| {
+        let mut child = Command::new(cmd)
+            .args(*args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut stdout = child.stdout.take()?;
+        let (send, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = send.send(stdout.read_to_end(&mut output).map(|_| output));
+        });
+
+        let output = output.recv_timeout(TOOL_TIMEOUT).ok().and_then(Result::ok);
+        let timeout = if output.is_some() {
+            TOOL_TIMEOUT
+        } else {
+            Duration::ZERO
+        };
+        let status = wait_timeout(&mut child, timeout)?;
+        let output = output.filter(|_| status.success())?;
+        Some(String::from_utf8_lossy(&output).replace("\r\n", "\n"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::Command;
+
+    #[test]
+    fn test_osc52_sequence() {
+        let mut out = String::new();
+        SetClipboard("héllo\nworld").write_ansi(&mut out).unwrap();
+        assert_eq!(out, "\x1b]52;c;aMOpbGxvCndvcmxk\x07");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_tool_is_killed() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        assert!(wait_timeout(&mut child, Duration::from_millis(50)).is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+pub const APP_NAME: &str = "noted";
+pub const DB_ENV_VAR: &str = "NOTED_DB";
+const DB_FILE_NAME: &str = "notes.json";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const DEFAULT_BACKUPS: usize = 3;
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    pub db: Option<PathBuf>,
+}
+
+impl Args {
+    // parse command line arguments, skipping the program name
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self> {
+        let mut parsed = Args::default();
+        let _ = args.next();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--db" => {
+                    let path = args.next().context("--db expects a file path")?;
+                    parsed.db = Some(PathBuf::from(path));
+                }
+                _ => match arg.strip_prefix("--db=") {
+                    Some(path) => parsed.db = Some(PathBuf::from(path)),
+                    None => anyhow::bail!("Unknown argument: {}", arg),
+                },
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+// columns of the notes table in the main view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Id,
+    Title,
+    CreatedAt,
+    UpdatedAt,
+    Edits,
+    Words,
+    Tags,
+}
+
+// key handling of the note editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EditingMode {
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+// how long lines are shown in the note editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WrapMode {
+    // wrap onto the next screen row
+    #[default]
+    Soft,
+    // one screen row per line, scrolling sideways to follow the cursor
+    None,
+}
+
+// what Up and Down move through when a line is wrapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerticalMovement {
+    #[default]
+    Visual,
+    Logical,
+}
+
+// how the note editor highlights the text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Syntax {
+    #[default]
+    Markdown,
+    Plain,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub db_path: Option<PathBuf>,
+    // number of `.bak` copies kept when saving, 0 disables backups
+    pub backups: usize,
+    pub columns: Vec<Column>,
+    // days a note stays in the trash before it is purged, 0 keeps it forever
+    pub trash_retention_days: u32,
+    // copy to the system clipboard with the OSC 52 escape sequence
+    pub osc52: bool,
+    // also copy and paste through wl-copy/wl-paste or xclip when installed,
+    // off by default since the editor waits on them
+    pub clipboard_tools: bool,
+    pub editing_mode: EditingMode,
+    pub wrap_mode: WrapMode,
+    pub vertical_movement: VerticalMovement,
+    pub syntax: Syntax,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            db_path: None,
+            backups: DEFAULT_BACKUPS,
+            columns: vec![
+                Column::Id,
+                Column::Title,
+                Column::CreatedAt,
+                Column::UpdatedAt,
+                Column::Tags,
+            ],
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            osc52: true,
+            clipboard_tools: false,
+            editing_mode: EditingMode::Default,
+            wrap_mode: WrapMode::Soft,
+            vertical_movement: VerticalMovement::Visual,
+            syntax: Syntax::Markdown,
+        }
+    }
+}
+
+impl Config {
+    // default location: $XDG_CONFIG_HOME/noted/config.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_NAME).join(CONFIG_FILE_NAME))
+    }
+
+    // a missing config file is not an error, we just fall back to defaults
+    pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read config file {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Couldn't parse config file {}", path.display()))
+    }
+}
+
+// resolve the notes database: --db flag, then NOTED_DB, then config file,
+// then $XDG_DATA_HOME/noted/notes.json
+pub fn resolve_db_path(
+    cli: Option<PathBuf>,
+    env: Option<PathBuf>,
+    config: &Config,
+) -> Result<PathBuf> {
+    if let Some(path) = cli.or(env).or_else(|| config.db_path.clone()) {
+        return Ok(path);
+    }
+
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_NAME).join(DB_FILE_NAME))
+        .context("Couldn't determine a data directory for the notes database")
+}
+
+pub fn db_path_from_env() -> Option<PathBuf> {
+    env::var_os(DB_ENV_VAR)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+// create any missing parent directories so the first save succeeds
+pub fn ensure_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Couldn't create directory {}", parent.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_parse_db_flag() {
+        let parsed = Args::parse(args(&["noted", "--db", "a.json"])).unwrap();
+        assert_eq!(parsed.db, Some(PathBuf::from("a.json")));
+
+        let parsed = Args::parse(args(&["noted", "--db=b.json"])).unwrap();
+        assert_eq!(parsed.db, Some(PathBuf::from("b.json")));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_args() {
+        assert!(Args::parse(args(&["noted", "--nope"])).is_err());
+        assert!(Args::parse(args(&["noted", "--db"])).is_err());
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let config = Config {
+            db_path: Some(PathBuf::from("config.json")),
+            ..Config::default()
+        };
+        let cli = Some(PathBuf::from("cli.json"));
+        let env = Some(PathBuf::from("env.json"));
+
+        let path = resolve_db_path(cli, env.clone(), &config).unwrap();
+        assert_eq!(path, PathBuf::from("cli.json"));
+
+        let path = resolve_db_path(None, env, &config).unwrap();
+        assert_eq!(path, PathBuf::from("env.json"));
+
+        let path = resolve_db_path(None, None, &config).unwrap();
+        assert_eq!(path, PathBuf::from("config.json"));
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: Config = toml::from_str("db_path = \"/tmp/notes.json\"").unwrap();
+        assert_eq!(config.db_path, Some(PathBuf::from("/tmp/notes.json")));
+        assert_eq!(config.backups, DEFAULT_BACKUPS);
+
+        let config: Config = toml::from_str("backups = 0").unwrap();
+        assert_eq!(config.backups, 0);
+
+        let config: Config = toml::from_str("columns = [\"title\", \"words\"]").unwrap();
+        assert_eq!(config.columns, vec![Column::Title, Column::Words]);
+
+        let config: Config = toml::from_str("osc52 = false").unwrap();
+        assert!(!config.osc52);
+        assert!(!config.clipboard_tools);
+
+        let config: Config = toml::from_str("clipboard_tools = true").unwrap();
+        assert!(config.osc52 && config.clipboard_tools);
+
+        let config: Config = toml::from_str("editing_mode = \"vim\"").unwrap();
+        assert_eq!(config.editing_mode, EditingMode::Vim);
+
+        let config: Config =
+            toml::from_str("wrap_mode = \"none\"\nvertical_movement = \"logical\"").unwrap();
+        assert_eq!(config.wrap_mode, WrapMode::None);
+        assert_eq!(config.vertical_movement, VerticalMovement::Logical);
+
+        let config: Config = toml::from_str("syntax = \"plain\"").unwrap();
+        assert_eq!(config.syntax, Syntax::Plain);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -6,12 +6,16 @@ use std::{
     time::{Duration, Instant},
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum EventType {
     Tick,
     Key(KeyEvent),
     Mouse(MouseEvent),
     Resize(u16, u16),
+    // text pasted into the terminal while bracketed paste is enabled
+    Paste(String),
+    FocusGained,
+    FocusLost,
 }
 
 #[derive(Debug)]
@@ -55,7 +59,11 @@ impl EventHandler {
 
                             Event::Resize(w, h) => sender.send(EventType::Resize(w, h)),
 
-                            _ => unimplemented!(),
+                            Event::Paste(text) => sender.send(EventType::Paste(text)),
+
+                            Event::FocusGained => sender.send(EventType::FocusGained),
+
+                            Event::FocusLost => sender.send(EventType::FocusLost),
                         }
                         .expect("failed to send terminal event")
                     }
@@ -0,0 +1,114 @@
+// subsequence fuzzy matching for the quick-open switcher
+
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 32;
+const WORD_START_BONUS: i64 = 24;
+const GAP_PENALTY: i64 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    // char indices of the matched characters in the candidate
+    pub positions: Vec<usize>,
+}
+
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    idx == 0 || !chars[idx - 1].is_alphanumeric()
+}
+
+fn lowercase(ch: char) -> char {
+    ch.to_lowercase().next().unwrap_or(ch)
+}
+
+// true if `query` is a subsequence of `text[from..]`
+fn matches_from(text: &[char], query: &[char], from: usize) -> bool {
+    let mut rest = query.iter().peekable();
+    for ch in text.iter().skip(from) {
+        if rest.peek() == Some(&ch) {
+            rest.next();
+        }
+    }
+    rest.peek().is_none()
+}
+
+// match `query` as a case-insensitive subsequence of `candidate`, preferring
+// consecutive runs and characters at the start of words
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|&ch| lowercase(ch)).collect();
+    let query: Vec<char> = query
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .map(lowercase)
+        .collect();
+
+    let mut positions: Vec<usize> = Vec::new();
+    let mut score = 0;
+    let mut next = 0;
+
+    for (qi, &query_ch) in query.iter().enumerate() {
+        let first = (next..lower.len()).find(|&i| lower[i] == query_ch)?;
+        let continues_run = positions.last().is_some_and(|&last| first == last + 1);
+
+        // jump ahead to a word start if the rest of the query still fits after it
+        let idx = if continues_run || is_word_start(&chars, first) {
+            first
+        } else {
+            (first..lower.len())
+                .find(|&i| {
+                    lower[i] == query_ch
+                        && is_word_start(&chars, i)
+                        && matches_from(&lower, &query[qi + 1..], i + 1)
+                })
+                .unwrap_or(first)
+        };
+
+        score += MATCH_SCORE;
+        if is_word_start(&chars, idx) {
+            score += WORD_START_BONUS;
+        }
+        match positions.last() {
+            Some(&last) if idx == last + 1 => score += CONSECUTIVE_BONUS,
+            Some(&last) => score -= GAP_PENALTY * (idx - last - 1) as i64,
+            None => score -= GAP_PENALTY * idx as i64,
+        }
+
+        positions.push(idx);
+        next = idx + 1;
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(fuzzy_match("mtg", "Meeting notes").is_some());
+        assert!(fuzzy_match("xyz", "Meeting notes").is_none());
+        assert!(fuzzy_match("", "anything").unwrap().positions.is_empty());
+    }
+
+    #[test]
+    fn test_positions_are_char_indices() {
+        let found = fuzzy_match("CAFÉ", "Ça café").unwrap();
+        assert_eq!(found.positions, vec![3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_word_starts_and_runs_score_higher() {
+        let word_starts = fuzzy_match("mn", "meeting notes").unwrap();
+        let scattered = fuzzy_match("mn", "summing").unwrap();
+        assert!(word_starts.score > scattered.score);
+        assert_eq!(word_starts.positions, vec![0, 8]);
+
+        let found = fuzzy_match("ab", "zab c_a").unwrap();
+        assert_eq!(found.positions, vec![1, 2]);
+
+        let run = fuzzy_match("note", "notes").unwrap();
+        let gaps = fuzzy_match("note", "n o t e").unwrap();
+        assert!(run.score > gaps.score);
+    }
+}
@@ -0,0 +1,84 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// Columns in the editor and search results count grapheme clusters, what a
+// reader sees as one character: `é` written as `e` + U+0301, a flag or an
+// emoji joined with ZWJ are all one column. Screen positions use the display
+// width instead, where CJK and most emoji take two cells.
+
+pub fn len(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+pub fn nth(line: &str, col: usize) -> Option<&str> {
+    line.graphemes(true).nth(col)
+}
+
+// byte offset of a column, the end of the line if past it
+pub fn byte_index(line: &str, col: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(col)
+        .map_or(line.len(), |(i, _)| i)
+}
+
+// column of a byte offset, which must be on a char boundary
+pub fn col_index(line: &str, byte: usize) -> usize {
+    len(&line[..byte])
+}
+
+// cells taken on screen by the line up to a column
+pub fn width_to(line: &str, col: usize) -> usize {
+    line[..byte_index(line, col)].width()
+}
+
+// cells taken on screen by the columns `from..to` of a line
+pub fn width_between(line: &str, from: usize, to: usize) -> usize {
+    line[byte_index(line, from)..byte_index(line, to)].width()
+}
+
+// the column shown at a screen cell, the end of the line if it is shorter
+pub fn col_at_width(line: &str, width: usize) -> usize {
+    let mut used = 0;
+    for (col, grapheme) in line.graphemes(true).enumerate() {
+        used += grapheme.width();
+        if used > width {
+            return col;
+        }
+    }
+    len(line)
+}
+
+pub fn is_blank(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_are_graphemes() {
+        let line = "ae\u{301}b👍🏽x";
+        assert_eq!(len(line), 5);
+        assert_eq!(nth(line, 1), Some("e\u{301}"));
+        assert_eq!(byte_index(line, 2), 4);
+        assert_eq!(byte_index(line, 9), line.len());
+        assert_eq!(col_index(line, 4), 2);
+
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(len(family), 1);
+        assert_eq!(len("🇳🇴"), 1);
+    }
+
+    #[test]
+    fn test_display_width() {
+        let line = "a界e\u{301}b";
+        assert_eq!(width_to(line, 1), 1);
+        assert_eq!(width_to(line, 2), 3);
+        assert_eq!(width_to(line, 3), 4);
+        assert_eq!(col_at_width(line, 0), 0);
+        assert_eq!(col_at_width(line, 2), 1);
+        assert_eq!(col_at_width(line, 3), 2);
+        assert_eq!(col_at_width(line, 40), 4);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{fmt, ops::Range, rc::Rc};
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::{
+    config::Syntax,
+    tasks::{bullet_len, checkbox},
+};
+pub use code::CodeState;
+
+mod code;
+
+// Highlighters style a note one line at a time. Whatever a line leaves open
+// for the lines below, like a fenced code block, is carried in a `LineState`
+// so the editor can start highlighting from any line it has a state for.
+
+pub trait Highlighter: fmt::Debug {
+    // styles for byte ranges of a line, later ranges drawn over earlier ones.
+    // `state` is what the line above left and is updated for the line below
+    fn highlight(&self, line: &str, state: &mut LineState) -> Vec<(Range<usize>, Style)>;
+
+    // move the state past a line without styling it. The editor skips through
+    // lines far above the screen this way, so a highlighter that is slow to
+    // carry its state may only keep what is cheap and start over from there
+    fn skip(&self, line: &str, state: &mut LineState) {
+        self.highlight(line, state);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum LineState {
+    #[default]
+    Text,
+    // inside a block opened by `fence`, e.g. "```", tagged with a language.
+    // The language's grammar, if there is one, goes on from `code` or starts
+    // over when it was skipped
+    Block {
+        fence: String,
+        lang: String,
+        code: Option<CodeState>,
+    },
+}
+
+pub fn for_syntax(syntax: Syntax) -> Option<Rc<dyn Highlighter>> {
+    match syntax {
+        Syntax::Markdown => Some(Rc::new(Markdown)),
+        Syntax::Plain => None,
+    }
+}
+
+pub fn heading() -> Style {
+    Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD)
+}
+
+pub fn marker() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+pub fn code() -> Style {
+    Style::default().fg(Color::LightRed)
+}
+
+pub fn bullet() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+pub fn link() -> Style {
+    Style::default()
+        .fg(Color::LightBlue)
+        .add_modifier(Modifier::UNDERLINED)
+}
+
+#[derive(Debug, Default)]
+pub struct Markdown;
+
+impl Highlighter for Markdown {
+    fn highlight(&self, line: &str, state: &mut LineState) -> Vec<(Range<usize>, Style)> {
+        let all = 0..line.len();
+        if let LineState::Block { fence, lang, code } = state {
+            if closes(line, fence) {
+                *state = LineState::Text;
+                return vec![(all, marker())];
+            }
+            if code.is_none() {
+                *code = CodeState::new(lang);
+            }
+            return match code {
+                Some(code) => code.highlight(line),
+                None => vec![(all, self::code())],
+            };
+        }
+        if let Some((fence, lang)) = opens(line) {
+            let code = CodeState::new(&lang);
+            *state = LineState::Block { fence, lang, code };
+            return vec![(all, marker())];
+        }
+
+        let mut styles = Vec::new();
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let rest = &line[indent..];
+        if indent < 4 && is_heading(rest) {
+            return vec![(all, heading())];
+        }
+
+        // quote markers, then a list bullet and a checkbox
+        let mut at = indent;
+        while let Some(quoted) = line[at..].strip_prefix('>') {
+            styles.push((at..at + 1, marker()));
+            at = line.len() - quoted.trim_start_matches(' ').len();
+        }
+        if at > indent {
+            let quote = Style::default().add_modifier(Modifier::ITALIC);
+            styles.push((at..line.len(), quote));
+        }
+        if let Some(len) = bullet_len(&line[at..]) {
+            styles.push((at..at + len, bullet()));
+            at += len;
+            if let Some(checked) = checkbox(&line[at..]) {
+                styles.push((at..at + 3, Style::default().fg(Color::Green)));
+                // the text of a done task is struck through
+                if checked {
+                    let done = Style::default().add_modifier(Modifier::CROSSED_OUT);
+                    styles.push((at + 3..line.len(), done.fg(Color::DarkGray)));
+                }
+                at += 3;
+            }
+        }
+
+        inline(line, at, &mut styles);
+        styles
+    }
+
+    // only the fences are followed, code grammars are dropped
+    fn skip(&self, line: &str, state: &mut LineState) {
+        match state {
+            LineState::Block { fence, .. } if closes(line, fence) => *state = LineState::Text,
+            LineState::Block { code, .. } => *code = None,
+            LineState::Text => {
+                if let Some((fence, lang)) = opens(line) {
+                    *state = LineState::Block {
+                        fence,
+                        lang,
+                        code: None,
+                    };
+                }
+            }
+        }
+    }
+}
+
+fn is_heading(line: &str) -> bool {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    (1..=6).contains(&hashes) && line[hashes..].chars().next().is_none_or(|ch| ch == ' ')
+}
+
+// the fence and language of a line opening a code block, like "```rust"
+fn opens(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = trimmed
+        .chars()
+        .next()
+        .filter(|&ch| ch == '`' || ch == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(ch).len();
+    let info = trimmed[len..].trim();
+    // a backtick fence can't have backticks after it, that's inline code
+    if len < 3 || (ch == '`' && info.contains('`')) {
+        return None;
+    }
+    let lang = info.split_whitespace().next().unwrap_or_default();
+    Some((trimmed[..len].to_string(), lang.to_string()))
+}
+
+fn closes(line: &str, fence: &str) -> bool {
+    let trimmed = line.trim();
+    let ch = fence.chars().next().unwrap_or('`');
+    line.len() - line.trim_start_matches(' ').len() <= 3
+        && trimmed.len() >= fence.len()
+        && trimmed.chars().all(|c| c == ch)
+}
+
+// code spans, links and emphasis in the line from `start`. They don't nest,
+// whatever comes first takes the text up to its end
+fn inline(line: &str, start: usize, styles: &mut Vec<(Range<usize>, Style)>) {
+    let bytes = line.as_bytes();
+    let mut at = start;
+    while at < line.len() {
+        let found = match bytes[at] {
+            b'`' => code_span(line, at).map(|end| vec![(at..end, code())]),
+            b'[' => link_at(line, at),
+            b'*' | b'_' => emphasis(line, at),
+            _ => None,
+        };
+        match found {
+            Some(found) => {
+                at = found
+                    .iter()
+                    .map(|(range, _)| range.end)
+                    .max()
+                    .unwrap_or(at + 1);
+                styles.extend(found);
+            }
+            // skip a whole run of markers so `**` isn't read as two `*`
+            None => {
+                let ch = bytes[at];
+                let run = match ch {
+                    b'`' | b'*' | b'_' => bytes[at..].iter().take_while(|&&b| b == ch).count(),
+                    _ => line[at..].chars().next().map_or(1, char::len_utf8),
+                };
+                at += run;
+            }
+        }
+    }
+}
+
+// the end of a code span opened by the backticks at `at`, closed by a run
+// of as many backticks
+fn code_span(line: &str, at: usize) -> Option<usize> {
+    let run = line[at..].len() - line[at..].trim_start_matches('`').len();
+    let mut from = at + run;
+    while let Some(i) = line[from..].find('`') {
+        let start = from + i;
+        let len = line[start..].len() - line[start..].trim_start_matches('`').len();
+        if len == run {
+            return Some(start + len);
+        }
+        from = start + len;
+    }
+    None
+}
+
+// `[text](url)`, the text styled as a link and the url as markup
+fn link_at(line: &str, at: usize) -> Option<Vec<(Range<usize>, Style)>> {
+    let close = at + line[at..].find("](")?;
+    if line[at + 1..close].contains(['[', ']']) {
+        return None;
+    }
+    let end = close + line[close..].find(')')? + 1;
+    Some(vec![(at..close + 1, link()), (close + 1..end, marker())])
+}
+
+// `**strong**` or `*emphasis*`, also with `_`. The text can't start or end
+// with a space, and `_` inside a word like snake_case isn't emphasis
+fn emphasis(line: &str, at: usize) -> Option<Vec<(Range<usize>, Style)>> {
+    let ch = line[at..].chars().next()?;
+    let run = line[at..].len() - line[at..].trim_start_matches(ch).len();
+    let len = run.min(2);
+    let word = |ch: Option<char>| ch.is_some_and(char::is_alphanumeric);
+    if ch == '_' && word(line[..at].chars().next_back()) {
+        return None;
+    }
+    let marker = &line[at..at + len];
+    let text = at + len;
+    if line[text..].starts_with(' ') {
+        return None;
+    }
+
+    let mut from = text;
+    while let Some(i) = line[from..].find(marker) {
+        let end = from + i;
+        let after = line[end + len..].chars().next();
+        let valid = end > text
+            && !line[..end].ends_with(' ')
+            && !(ch == '_' && word(after))
+            && (len == 2 || after != Some(ch));
+        if valid {
+            let modifier = match len {
+                2 => Modifier::BOLD,
+                _ => Modifier::ITALIC,
+            };
+            let style = Style::default().add_modifier(modifier);
+            return Some(vec![(at..end + len, style)]);
+        }
+        from = end + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of each styled range, to compare without spelling out styles
+    fn styled<'a>(line: &'a str, state: &mut LineState) -> Vec<&'a str> {
+        Markdown
+            .highlight(line, state)
+            .into_iter()
+            .map(|(range, _)| &line[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_block_level_markup() {
+        let mut state = LineState::Text;
+        assert_eq!(styled("## Title", &mut state), vec!["## Title"]);
+        assert_eq!(styled("#hashtag", &mut state), Vec::<&str>::new());
+        assert_eq!(
+            styled("> - [x] done", &mut state),
+            vec![">", "- [x] done", "- ", "[x]", " done"]
+        );
+        assert_eq!(styled("1. item", &mut state), vec!["1. "]);
+        assert_eq!(styled("-not a bullet", &mut state), Vec::<&str>::new());
+
+        assert_eq!(styled("```rust", &mut state), vec!["```rust"]);
+        assert_eq!(
+            state,
+            LineState::Block {
+                fence: "```".into(),
+                lang: "rust".into(),
+                code: CodeState::new("rust"),
+            }
+        );
+        // the language's grammar styles the code
+        let styles = Markdown.highlight("# [attr]", &mut state);
+        assert!(styles.iter().all(|&(_, style)| style != heading()));
+        styled("```", &mut state);
+        assert_eq!(state, LineState::Text);
+
+        // skipping keeps to the fences, the grammar starts over after it
+        Markdown.skip("```rust", &mut state);
+        Markdown.skip("let s = \"", &mut state);
+        assert!(matches!(&state, LineState::Block { code: None, .. }));
+        styled("x", &mut state);
+        assert_eq!(
+            state,
+            LineState::Block {
+                fence: "```".into(),
+                lang: "rust".into(),
+                code: CodeState::new("rust").map(|mut code| {
+                    code.highlight("x");
+                    code
+                }),
+            }
+        );
+        Markdown.skip("```", &mut state);
+        assert_eq!(state, LineState::Text);
+
+        // nothing is markup inside a block without a language, and a shorter
+        // fence doesn't close it
+        styled("````", &mut state);
+        assert_eq!(
+            styled("# not a heading `x`", &mut state),
+            vec!["# not a heading `x`"]
+        );
+        styled("~~~", &mut state);
+        styled("```", &mut state);
+        assert_ne!(state, LineState::Text);
+        styled("````", &mut state);
+        assert_eq!(state, LineState::Text);
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        let mut state = LineState::Text;
+        assert_eq!(
+            styled("a `b` and ``c ` d`` e", &mut state),
+            vec!["`b`", "``c ` d``"]
+        );
+        assert_eq!(
+            styled("see [the docs](http://x.y) now", &mut state),
+            vec!["[the docs]", "(http://x.y)"]
+        );
+        assert_eq!(
+            styled("**bold** and *it* or _it_ in snake_case_name", &mut state),
+            vec!["**bold**", "*it*", "_it_"]
+        );
+        assert_eq!(styled("2 * 3 * 4, a ** b", &mut state), Vec::<&str>::new());
+        assert_eq!(styled("`unclosed", &mut state), Vec::<&str>::new());
+    }
+}
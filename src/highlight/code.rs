@@ -0,0 +1,118 @@
+use std::{ops::Range, sync::OnceLock};
+
+use ratatui::style::{Color, Modifier, Style};
+use syntect::{
+    highlighting::{
+        self, FontStyle, HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet,
+    },
+    parsing::{ParseState, ScopeStack, SyntaxSet},
+};
+
+// Code in fenced blocks is colored with the grammars and theme bundled with
+// syntect. A grammar parses a line from where the line above left it, so the
+// parser's state is carried from line to line in a `CodeState`.
+
+const THEME: &str = "base16-ocean.dark";
+
+// loaded on first use, unpacking the grammars takes a moment
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn highlighter() -> &'static Highlighter<'static> {
+    static THEME_SET: OnceLock<Theme> = OnceLock::new();
+    static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+    HIGHLIGHTER.get_or_init(|| {
+        let theme = THEME_SET.get_or_init(|| {
+            ThemeSet::load_defaults()
+                .themes
+                .remove(THEME)
+                .unwrap_or_default()
+        });
+        Highlighter::new(theme)
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeState {
+    parse: ParseState,
+    highlight: HighlightState,
+}
+
+impl CodeState {
+    // the state at the start of a block, None for a language without a
+    // grammar. Languages are named like a fence's tag: `rust`, `sql`, `sh`
+    pub fn new(lang: &str) -> Option<Self> {
+        let syntax = syntaxes().find_syntax_by_token(lang)?;
+        Some(CodeState {
+            parse: ParseState::new(syntax),
+            highlight: HighlightState::new(highlighter(), ScopeStack::new()),
+        })
+    }
+
+    // styles for the byte ranges of a line, moving the state to the next line
+    pub fn highlight(&mut self, line: &str) -> Vec<(Range<usize>, Style)> {
+        // the bundled grammars expect each line to end in a newline
+        let line = format!("{line}\n");
+        let Ok(ops) = self.parse.parse_line(&line, syntaxes()) else {
+            return Vec::new();
+        };
+        let mut styles: Vec<(Range<usize>, Style)> = Vec::new();
+        let mut at = 0;
+        for (style, text) in HighlightIterator::new(&mut self.highlight, &ops, &line, highlighter())
+        {
+            let (start, end) = (at, (at + text.len()).min(line.len() - 1));
+            at += text.len();
+            if start >= end {
+                continue;
+            }
+            let style = convert(style);
+            match styles.last_mut() {
+                Some((range, last)) if *last == style => range.end = end,
+                _ => styles.push((start..end, style)),
+            }
+        }
+        styles
+    }
+}
+
+// the theme's foreground and font style, the terminal's background is kept
+fn convert(style: highlighting::Style) -> Style {
+    let color = style.foreground;
+    let mut converted = Style::default().fg(Color::Rgb(color.r, color.g, color.b));
+    for (font, modifier) in [
+        (FontStyle::BOLD, Modifier::BOLD),
+        (FontStyle::ITALIC, Modifier::ITALIC),
+        (FontStyle::UNDERLINE, Modifier::UNDERLINED),
+    ] {
+        if style.font_style.contains(font) {
+            converted = converted.add_modifier(modifier);
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlights_across_lines() {
+        assert!(CodeState::new("not-a-language").is_none());
+        let mut state = CodeState::new("rust").unwrap();
+        let line = "let s = \"a";
+        let styles = state.highlight(line);
+        assert_eq!(
+            styles.first().map(|(range, _)| &line[range.clone()]),
+            Some("let")
+        );
+        assert_eq!(styles.last().map(|(range, _)| range.end), Some(line.len()));
+
+        // the string is still open on the next line, so it is colored alike
+        let string = styles.last().unwrap().1;
+        let styles = state.highlight("b\"; x");
+        assert_eq!(styles[0], (0..1, string));
+        assert_ne!(styles.last().unwrap().1, string);
+    }
+}
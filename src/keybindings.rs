@@ -0,0 +1,860 @@
+use anyhow::{bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::APP_NAME;
+
+const KEYBINDINGS_FILE_NAME: &str = "keybindings.toml";
+
+// binding a key to this removes the default binding
+const UNBIND: &str = "none";
+
+// where a key is pressed, each one is a table in the keybindings file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Notes,
+    Notebooks,
+    Trash,
+    Search,
+    Tasks,
+    Editor,
+    // the find bar while the query or the replacement is being typed
+    Find,
+    Replace,
+    QuickOpen,
+    // a one-line prompt, like a note's title or tags
+    Prompt,
+    Confirm,
+    Recovery,
+}
+
+const SCOPES: &[(Scope, &str)] = &[
+    (Scope::Notes, "notes"),
+    (Scope::Notebooks, "notebooks"),
+    (Scope::Trash, "trash"),
+    (Scope::Search, "search"),
+    (Scope::Tasks, "tasks"),
+    (Scope::Editor, "editor"),
+    (Scope::Find, "find"),
+    (Scope::Replace, "replace"),
+    (Scope::QuickOpen, "quick_open"),
+    (Scope::Prompt, "prompt"),
+    (Scope::Confirm, "confirm"),
+    (Scope::Recovery, "recovery"),
+];
+
+impl Scope {
+    pub fn name(self) -> &'static str {
+        SCOPES.iter().find(|(scope, _)| *scope == self).unwrap().1
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        SCOPES
+            .iter()
+            .find(|(_, scope_name)| *scope_name == name)
+            .map(|(scope, _)| *scope)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Back,
+    QuickOpen,
+    Search,
+    FocusNotebooks,
+    FocusNotes,
+    NewNote,
+    OpenNote,
+    EditTags,
+    FilterTags,
+    ToggleFilterMode,
+    MoveNote,
+    DeleteNote,
+    ShowTrash,
+    ShowTasks,
+    PrevNote,
+    NextNote,
+    FirstNote,
+    LastNote,
+    NewNotebook,
+    RenameNotebook,
+    MoveNotebook,
+    ToggleNotebook,
+    CollapseNotebook,
+    ExpandNotebook,
+    PrevNotebook,
+    NextNotebook,
+    RestoreTrashed,
+    PurgeTrashed,
+    EmptyTrash,
+    PrevTrashed,
+    NextTrashed,
+    OpenResult,
+    ToggleCase,
+    ToggleWholeWord,
+    ToggleRegex,
+    PrevResult,
+    NextResult,
+    OpenTask,
+    CompleteTask,
+    PrevTask,
+    NextTask,
+    Save,
+    Undo,
+    Redo,
+    Cut,
+    Copy,
+    Paste,
+    Find,
+    Preview,
+    PreviewUp,
+    PreviewDown,
+    ToggleTask,
+    FindNext,
+    FindPrev,
+    Replace,
+    ReplaceAll,
+    FocusReplace,
+    FocusFind,
+    FindCase,
+    FindWholeWord,
+    FindRegex,
+    OpenMatch,
+    PrevMatch,
+    NextMatch,
+    Submit,
+    Close,
+    ConfirmYes,
+    ConfirmNo,
+    RestoreBackup,
+    StartEmpty,
+    ToggleDetails,
+}
+
+// name used in the keybindings file and the label shown in the nav bar, in
+// the order hints are shown. An empty label keeps the action out of the hints
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "app.quit", "quit"),
+    (Action::Back, "app.back", "back"),
+    (Action::FocusNotebooks, "view.notebooks", "notebooks"),
+    (Action::FocusNotes, "view.notes", "notes"),
+    (Action::QuickOpen, "app.quick_open", "open"),
+    (Action::Search, "view.search", "search"),
+    (Action::NewNote, "note.new", "new"),
+    (Action::OpenNote, "note.open", ""),
+    (Action::EditTags, "note.tags", "tags"),
+    (Action::FilterTags, "notes.filter", "filter"),
+    (Action::ToggleFilterMode, "notes.filter_mode", ""),
+    (Action::MoveNote, "note.move", "move"),
+    (Action::DeleteNote, "note.delete", "delete"),
+    (Action::ShowTrash, "view.trash", "trash"),
+    (Action::ShowTasks, "view.tasks", "tasks"),
+    (Action::PrevNote, "notes.prev", ""),
+    (Action::NextNote, "notes.next", ""),
+    (Action::FirstNote, "notes.first", ""),
+    (Action::LastNote, "notes.last", ""),
+    (Action::NewNotebook, "notebook.new", "new"),
+    (Action::RenameNotebook, "notebook.rename", "rename"),
+    (Action::MoveNotebook, "notebook.move", "move"),
+    (Action::ToggleNotebook, "notebook.toggle", "fold"),
+    (Action::CollapseNotebook, "notebook.collapse", ""),
+    (Action::ExpandNotebook, "notebook.expand", ""),
+    (Action::PrevNotebook, "notebooks.prev", ""),
+    (Action::NextNotebook, "notebooks.next", ""),
+    (Action::RestoreTrashed, "trash.restore", "restore"),
+    (Action::PurgeTrashed, "trash.delete", "delete permanently"),
+    (Action::EmptyTrash, "trash.empty", "empty trash"),
+    (Action::PrevTrashed, "trash.prev", ""),
+    (Action::NextTrashed, "trash.next", ""),
+    (Action::OpenResult, "search.open", "open"),
+    (Action::ToggleCase, "search.case", "case"),
+    (Action::ToggleWholeWord, "search.whole_word", "whole word"),
+    (Action::ToggleRegex, "search.regex", "regex"),
+    (Action::PrevResult, "search.prev", ""),
+    (Action::NextResult, "search.next", ""),
+    (Action::OpenTask, "tasks.open", "open"),
+    (Action::CompleteTask, "tasks.complete", "done"),
+    (Action::PrevTask, "tasks.prev", ""),
+    (Action::NextTask, "tasks.next", ""),
+    (Action::Save, "editor.save", "save"),
+    (Action::Undo, "editor.undo", "undo"),
+    (Action::Redo, "editor.redo", "redo"),
+    (Action::Cut, "editor.cut", "cut"),
+    (Action::Copy, "editor.copy", "copy"),
+    (Action::Paste, "editor.paste", "paste"),
+    (Action::Find, "editor.find", "find"),
+    (Action::Preview, "editor.preview", "preview"),
+    (Action::PreviewUp, "editor.preview_up", ""),
+    (Action::PreviewDown, "editor.preview_down", ""),
+    (Action::ToggleTask, "editor.toggle_task", "task"),
+    (Action::FindNext, "find.next", "next"),
+    (Action::FindPrev, "find.prev", "prev"),
+    (Action::Replace, "find.replace", "replace"),
+    (Action::ReplaceAll, "find.replace_all", "replace all"),
+    (Action::FocusReplace, "find.focus_replace", "replace"),
+    (Action::FocusFind, "find.focus_find", "find"),
+    (Action::FindCase, "find.case", "case"),
+    (Action::FindWholeWord, "find.whole_word", "whole word"),
+    (Action::FindRegex, "find.regex", "regex"),
+    (Action::OpenMatch, "quick_open.open", "open"),
+    (Action::PrevMatch, "quick_open.prev", ""),
+    (Action::NextMatch, "quick_open.next", ""),
+    (Action::Submit, "prompt.submit", "ok"),
+    (Action::Close, "app.close", "close"),
+    (Action::ConfirmYes, "confirm.yes", "yes"),
+    (Action::ConfirmNo, "confirm.no", "no"),
+    (Action::RestoreBackup, "recovery.backup", "open backup"),
+    (
+        Action::StartEmpty,
+        "recovery.empty",
+        "start empty without overwriting the database",
+    ),
+    (
+        Action::ToggleDetails,
+        "recovery.details",
+        "show/hide error details",
+    ),
+];
+
+impl Action {
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.iter().map(|(action, _, _)| *action)
+    }
+
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, ..)| *action == self)
+            .unwrap()
+            .1
+    }
+
+    pub fn label(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, ..)| *action == self)
+            .unwrap()
+            .2
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, ..)| *action)
+    }
+
+    // the scopes an action makes sense in
+    fn scopes(self) -> &'static [Scope] {
+        use Scope::*;
+        match self {
+            Action::Quit => &[Notes, Notebooks, Trash, Search, Tasks, Editor, Recovery],
+            Action::Back => &[Notes, Notebooks, Trash, Search, Tasks, Editor],
+            Action::QuickOpen => &[Notes, Editor],
+            Action::Search => &[Notes, Search],
+            Action::FocusNotes => &[Notebooks],
+            Action::FocusNotebooks
+            | Action::NewNote
+            | Action::OpenNote
+            | Action::EditTags
+            | Action::FilterTags
+            | Action::ToggleFilterMode
+            | Action::MoveNote
+            | Action::DeleteNote
+            | Action::ShowTrash
+            | Action::ShowTasks
+            | Action::PrevNote
+            | Action::NextNote
+            | Action::FirstNote
+            | Action::LastNote => &[Notes],
+            Action::NewNotebook
+            | Action::RenameNotebook
+            | Action::MoveNotebook
+            | Action::ToggleNotebook
+            | Action::CollapseNotebook
+            | Action::ExpandNotebook
+            | Action::PrevNotebook
+            | Action::NextNotebook => &[Notebooks],
+            Action::RestoreTrashed
+            | Action::PurgeTrashed
+            | Action::EmptyTrash
+            | Action::PrevTrashed
+            | Action::NextTrashed => &[Trash],
+            Action::OpenResult
+            | Action::ToggleCase
+            | Action::ToggleWholeWord
+            | Action::ToggleRegex
+            | Action::PrevResult
+            | Action::NextResult => &[Search],
+            Action::OpenTask | Action::CompleteTask | Action::PrevTask | Action::NextTask => {
+                &[Tasks]
+            }
+            Action::Save
+            | Action::Undo
+            | Action::Redo
+            | Action::Cut
+            | Action::Copy
+            | Action::Paste
+            | Action::Find
+            | Action::Preview
+            | Action::PreviewUp
+            | Action::PreviewDown
+            | Action::ToggleTask => &[Editor],
+            Action::FindNext
+            | Action::FindPrev
+            | Action::ReplaceAll
+            | Action::FindCase
+            | Action::FindWholeWord
+            | Action::FindRegex => &[Find, Replace],
+            Action::FocusReplace => &[Find],
+            Action::Replace | Action::FocusFind => &[Replace],
+            Action::OpenMatch | Action::PrevMatch | Action::NextMatch => &[QuickOpen],
+            Action::Submit => &[Prompt],
+            Action::Close => &[Find, Replace, QuickOpen, Prompt],
+            Action::ConfirmYes | Action::ConfirmNo => &[Confirm],
+            Action::RestoreBackup | Action::StartEmpty | Action::ToggleDetails => &[Recovery],
+        }
+    }
+}
+
+const DEFAULT_BINDINGS: &[(Scope, &str, Action)] = &[
+    (Scope::Notes, "q", Action::Quit),
+    (Scope::Notes, "ctrl-c", Action::Quit),
+    (Scope::Notes, "esc", Action::Back),
+    (Scope::Notes, "tab", Action::FocusNotebooks),
+    (Scope::Notes, "ctrl-p", Action::QuickOpen),
+    (Scope::Notes, "/", Action::Search),
+    (Scope::Notes, "n", Action::NewNote),
+    (Scope::Notes, "enter", Action::OpenNote),
+    (Scope::Notes, "t", Action::EditTags),
+    (Scope::Notes, "f", Action::FilterTags),
+    (Scope::Notes, "F", Action::ToggleFilterMode),
+    (Scope::Notes, "m", Action::MoveNote),
+    (Scope::Notes, "d", Action::DeleteNote),
+    (Scope::Notes, "T", Action::ShowTrash),
+    (Scope::Notes, "ctrl-t", Action::ShowTasks),
+    (Scope::Notes, "up", Action::PrevNote),
+    (Scope::Notes, "down", Action::NextNote),
+    (Scope::Notes, "g g", Action::FirstNote),
+    (Scope::Notes, "G", Action::LastNote),
+    (Scope::Notebooks, "q", Action::Quit),
+    (Scope::Notebooks, "ctrl-c", Action::Quit),
+    (Scope::Notebooks, "esc", Action::Quit),
+    (Scope::Notebooks, "tab", Action::FocusNotes),
+    (Scope::Notebooks, "N", Action::NewNotebook),
+    (Scope::Notebooks, "r", Action::RenameNotebook),
+    (Scope::Notebooks, "m", Action::MoveNotebook),
+    (Scope::Notebooks, "enter", Action::ToggleNotebook),
+    (Scope::Notebooks, "space", Action::ToggleNotebook),
+    (Scope::Notebooks, "left", Action::CollapseNotebook),
+    (Scope::Notebooks, "right", Action::ExpandNotebook),
+    (Scope::Notebooks, "up", Action::PrevNotebook),
+    (Scope::Notebooks, "down", Action::NextNotebook),
+    (Scope::Trash, "q", Action::Back),
+    (Scope::Trash, "esc", Action::Back),
+    (Scope::Trash, "ctrl-c", Action::Quit),
+    (Scope::Trash, "r", Action::RestoreTrashed),
+    (Scope::Trash, "enter", Action::RestoreTrashed),
+    (Scope::Trash, "d", Action::PurgeTrashed),
+    (Scope::Trash, "delete", Action::PurgeTrashed),
+    (Scope::Trash, "E", Action::EmptyTrash),
+    (Scope::Trash, "up", Action::PrevTrashed),
+    (Scope::Trash, "down", Action::NextTrashed),
+    (Scope::Search, "q", Action::Back),
+    (Scope::Search, "esc", Action::Back),
+    (Scope::Search, "ctrl-c", Action::Quit),
+    (Scope::Search, "enter", Action::OpenResult),
+    (Scope::Search, "/", Action::Search),
+    (Scope::Search, "c", Action::ToggleCase),
+    (Scope::Search, "w", Action::ToggleWholeWord),
+    (Scope::Search, "x", Action::ToggleRegex),
+    (Scope::Search, "up", Action::PrevResult),
+    (Scope::Search, "down", Action::NextResult),
+    (Scope::Tasks, "q", Action::Back),
+    (Scope::Tasks, "esc", Action::Back),
+    (Scope::Tasks, "ctrl-c", Action::Quit),
+    (Scope::Tasks, "enter", Action::OpenTask),
+    (Scope::Tasks, "space", Action::CompleteTask),
+    (Scope::Tasks, "x", Action::CompleteTask),
+    (Scope::Tasks, "up", Action::PrevTask),
+    (Scope::Tasks, "down", Action::NextTask),
+    (Scope::Editor, "esc", Action::Back),
+    (Scope::Editor, "ctrl-q", Action::Quit),
+    (Scope::Editor, "ctrl-s", Action::Save),
+    (Scope::Editor, "ctrl-p", Action::QuickOpen),
+    (Scope::Editor, "ctrl-z", Action::Undo),
+    (Scope::Editor, "ctrl-y", Action::Redo),
+    (Scope::Editor, "ctrl-r", Action::Redo),
+    (Scope::Editor, "ctrl-x", Action::Cut),
+    (Scope::Editor, "ctrl-c", Action::Copy),
+    (Scope::Editor, "ctrl-v", Action::Paste),
+    (Scope::Editor, "ctrl-f", Action::Find),
+    (Scope::Editor, "alt-p", Action::Preview),
+    (Scope::Editor, "alt-up", Action::PreviewUp),
+    (Scope::Editor, "alt-down", Action::PreviewDown),
+    (Scope::Editor, "ctrl-t", Action::ToggleTask),
+    (Scope::Find, "enter", Action::FindNext),
+    (Scope::Find, "down", Action::FindNext),
+    (Scope::Find, "up", Action::FindPrev),
+    (Scope::Find, "ctrl-a", Action::ReplaceAll),
+    (Scope::Find, "tab", Action::FocusReplace),
+    (Scope::Find, "backtab", Action::FocusReplace),
+    (Scope::Find, "alt-c", Action::FindCase),
+    (Scope::Find, "alt-w", Action::FindWholeWord),
+    (Scope::Find, "alt-r", Action::FindRegex),
+    (Scope::Find, "esc", Action::Close),
+    (Scope::Find, "ctrl-c", Action::Close),
+    (Scope::Replace, "enter", Action::Replace),
+    (Scope::Replace, "down", Action::FindNext),
+    (Scope::Replace, "up", Action::FindPrev),
+    (Scope::Replace, "ctrl-a", Action::ReplaceAll),
+    (Scope::Replace, "tab", Action::FocusFind),
+    (Scope::Replace, "backtab", Action::FocusFind),
+    (Scope::Replace, "alt-c", Action::FindCase),
+    (Scope::Replace, "alt-w", Action::FindWholeWord),
+    (Scope::Replace, "alt-r", Action::FindRegex),
+    (Scope::Replace, "esc", Action::Close),
+    (Scope::Replace, "ctrl-c", Action::Close),
+    (Scope::QuickOpen, "enter", Action::OpenMatch),
+    (Scope::QuickOpen, "up", Action::PrevMatch),
+    (Scope::QuickOpen, "down", Action::NextMatch),
+    (Scope::QuickOpen, "esc", Action::Close),
+    (Scope::QuickOpen, "ctrl-c", Action::Close),
+    (Scope::Prompt, "enter", Action::Submit),
+    (Scope::Prompt, "esc", Action::Close),
+    (Scope::Prompt, "ctrl-c", Action::Close),
+    (Scope::Confirm, "y", Action::ConfirmYes),
+    (Scope::Confirm, "Y", Action::ConfirmYes),
+    (Scope::Confirm, "n", Action::ConfirmNo),
+    (Scope::Confirm, "N", Action::ConfirmNo),
+    (Scope::Confirm, "esc", Action::ConfirmNo),
+    (Scope::Recovery, "b", Action::RestoreBackup),
+    (Scope::Recovery, "e", Action::StartEmpty),
+    (Scope::Recovery, "d", Action::ToggleDetails),
+    (Scope::Recovery, "q", Action::Quit),
+    (Scope::Recovery, "esc", Action::Quit),
+    (Scope::Recovery, "ctrl-c", Action::Quit),
+];
+
+// a key with its modifiers. Shift is folded into the character, so `G` and
+// `shift-g` are the same chord
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Chord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let code = match code {
+            // terminals can't tell C-a from C-A
+            KeyCode::Char(ch) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(ch.to_ascii_lowercase())
+            }
+            KeyCode::Char(ch) if shift => KeyCode::Char(ch.to_ascii_uppercase()),
+            KeyCode::Tab if shift => KeyCode::BackTab,
+            code => code,
+        };
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Chord { code, modifiers }
+    }
+
+    // e.g. `q`, `G`, `ctrl-s`, `alt-f`, `shift-tab`, `enter`
+    fn parse(spec: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = spec.split('-').collect();
+        // `-` and `ctrl--` name the minus key itself
+        if spec.ends_with('-') {
+            parts.truncate(parts.len().saturating_sub(2));
+            parts.push("-");
+        }
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .context("missing key")?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier \"{}\"", modifier),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("unknown key \"{}\"", key),
+                },
+            },
+        };
+        Ok(Chord::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for Chord {
+    fn from(event: KeyEvent) -> Self {
+        Chord::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "^")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(ch) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                write!(f, "{}", ch.to_ascii_uppercase())
+            }
+            KeyCode::Char(ch) => write!(f, "{}", ch),
+            KeyCode::BackTab => write!(f, "S-Tab"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+fn parse_sequence(spec: &str) -> Result<Vec<Chord>> {
+    let keys = spec
+        .split_whitespace()
+        .map(Chord::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        bail!("missing key");
+    }
+    Ok(keys)
+}
+
+// keys of a sequence as shown in the nav bar, e.g. `g g`
+pub fn describe(keys: &[Chord]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Binding {
+    scope: Scope,
+    keys: Vec<Chord>,
+    action: Action,
+    // from the keybindings file rather than the defaults
+    custom: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup {
+    Action(Action),
+    // the keys so far start a longer sequence
+    Pending,
+    Unbound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybindings {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(scope, spec, action)| Binding {
+                scope,
+                keys: parse_sequence(spec).expect("invalid default keybinding"),
+                action,
+                custom: false,
+            })
+            .collect();
+        Keybindings { bindings }
+    }
+}
+
+impl Keybindings {
+    // default location: $XDG_CONFIG_HOME/noted/keybindings.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join(APP_NAME).join(KEYBINDINGS_FILE_NAME))
+    }
+
+    // a missing keybindings file is not an error, the defaults are used
+    pub fn load() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Keybindings::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read keybindings file {}", path.display()))?;
+        Self::from_toml(&contents)
+            .with_context(|| format!("Invalid keybindings file {}", path.display()))
+    }
+
+    // bindings in the file replace the defaults for the same keys:
+    //
+    //   [notes]
+    //   x = "note.delete"
+    //   d = "none"
+    //   "ctrl-n" = "note.new"
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        let tables: BTreeMap<String, BTreeMap<String, String>> = toml::from_str(contents)?;
+        let mut keybindings = Keybindings::default();
+
+        for (scope_name, table) in &tables {
+            let scope = Scope::from_name(scope_name).with_context(|| {
+                let names: Vec<_> = SCOPES.iter().map(|(_, name)| *name).collect();
+                format!(
+                    "Unknown section [{}], expected one of {}",
+                    scope_name,
+                    names.join(", ")
+                )
+            })?;
+
+            let mut seen: Vec<(&str, Vec<Chord>)> = Vec::new();
+            for (spec, action_name) in table {
+                let keys = parse_sequence(spec)
+                    .with_context(|| format!("Invalid key \"{}\" in [{}]", spec, scope_name))?;
+                if let Some((other, _)) = seen.iter().find(|(_, other)| *other == keys) {
+                    bail!(
+                        "\"{}\" and \"{}\" in [{}] are the same key",
+                        other,
+                        spec,
+                        scope_name
+                    );
+                }
+                seen.push((spec, keys.clone()));
+
+                keybindings
+                    .bindings
+                    .retain(|binding| binding.scope != scope || binding.keys != keys);
+                if action_name == UNBIND {
+                    continue;
+                }
+                let action = Action::from_name(action_name).with_context(|| {
+                    format!("Unknown action \"{}\" for \"{}\"", action_name, spec)
+                })?;
+                if !action.scopes().contains(&scope) {
+                    bail!("{} can't be bound in [{}]", action_name, scope_name);
+                }
+                keybindings.bindings.push(Binding {
+                    scope,
+                    keys,
+                    action,
+                    custom: true,
+                });
+            }
+        }
+
+        keybindings.check_conflicts()?;
+        Ok(keybindings)
+    }
+
+    // a key that is also the start of a sequence could never finish it
+    fn check_conflicts(&self) -> Result<()> {
+        for short in &self.bindings {
+            let conflict = self.bindings.iter().find(|long| {
+                long.scope == short.scope
+                    && long.keys.len() > short.keys.len()
+                    && long.keys.starts_with(&short.keys)
+            });
+            if let Some(long) = conflict {
+                bail!(
+                    "\"{}\" ({}) in [{}] conflicts with \"{}\" ({}), bind one of them to \"{}\"",
+                    describe(&short.keys),
+                    short.action.name(),
+                    short.scope.name(),
+                    describe(&long.keys),
+                    long.action.name(),
+                    UNBIND
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn lookup(&self, scope: Scope, keys: &[Chord]) -> Lookup {
+        let mut pending = false;
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|binding| binding.scope == scope)
+        {
+            if binding.keys == keys {
+                return Lookup::Action(binding.action);
+            }
+            pending |= binding.keys.starts_with(keys);
+        }
+        match pending {
+            true => Lookup::Pending,
+            false => Lookup::Unbound,
+        }
+    }
+
+    // whether a binding from the keybindings file starts with the key. Those
+    // take priority over the keys of the editor's Vim or Emacs layer
+    pub fn is_custom(&self, scope: Scope, key: Chord) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.custom && binding.scope == scope && binding.keys[0] == key)
+    }
+
+    // every key sequence bound to an action, in the order they were bound
+    pub fn keys(&self, scope: Scope, action: Action) -> Vec<&[Chord]> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.scope == scope && binding.action == action)
+            .map(|binding| binding.keys.as_slice())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(spec: &str) -> Vec<Chord> {
+        parse_sequence(spec).unwrap()
+    }
+
+    #[test]
+    fn test_parse_chords() {
+        let ctrl_s = Chord::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert_eq!(keys("ctrl-s"), vec![ctrl_s]);
+        assert_eq!(keys("C-S"), vec![ctrl_s]);
+        assert_eq!(keys("shift-g"), keys("G"));
+        assert_eq!(keys("shift-tab")[0].code, KeyCode::BackTab);
+        assert_eq!(keys("ctrl--")[0].code, KeyCode::Char('-'));
+        assert_eq!(
+            Chord::from(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            keys("G")[0]
+        );
+        assert_eq!(describe(&keys("g g")), "g g");
+        assert_eq!(describe(&keys("ctrl-p alt-x esc")), "^P M-x Esc");
+        assert!(parse_sequence("hyper-x").is_err());
+        assert!(parse_sequence("nope").is_err());
+        assert!(parse_sequence(" ").is_err());
+    }
+
+    #[test]
+    fn test_defaults_and_sequences() {
+        let keybindings = Keybindings::default();
+        keybindings.check_conflicts().unwrap();
+        assert_eq!(
+            keybindings.lookup(Scope::Notes, &keys("n")),
+            Lookup::Action(Action::NewNote)
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Notes, &keys("g")),
+            Lookup::Pending
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Notes, &keys("g g")),
+            Lookup::Action(Action::FirstNote)
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Notes, &keys("g x")),
+            Lookup::Unbound
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Editor, &keys("n")),
+            Lookup::Unbound
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Find, &keys("alt-c")),
+            Lookup::Action(Action::FindCase)
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Replace, &keys("enter")),
+            Lookup::Action(Action::Replace)
+        );
+        assert_eq!(keybindings.lookup(Scope::Find, &keys("x")), Lookup::Unbound);
+        assert_eq!(
+            keybindings.keys(Scope::Notes, Action::Quit),
+            vec![keys("q").as_slice(), keys("ctrl-c").as_slice()]
+        );
+    }
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        let keybindings = Keybindings::from_toml(
+            "[notes]\nx = \"note.delete\"\nd = \"none\"\n\"g g\" = \"none\"\ng = \"notes.last\"\n\
+             [editor]\n\"ctrl-k ctrl-s\" = \"editor.save\"\n\
+             [confirm]\nenter = \"confirm.yes\"\n",
+        )
+        .unwrap();
+        let notes = |spec| keybindings.lookup(Scope::Notes, &keys(spec));
+        assert_eq!(notes("x"), Lookup::Action(Action::DeleteNote));
+        assert_eq!(notes("d"), Lookup::Unbound);
+        assert_eq!(notes("g"), Lookup::Action(Action::LastNote));
+        assert_eq!(
+            keybindings.lookup(Scope::Editor, &keys("ctrl-k")),
+            Lookup::Pending
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Editor, &keys("ctrl-k ctrl-s")),
+            Lookup::Action(Action::Save)
+        );
+        assert_eq!(
+            keybindings.lookup(Scope::Confirm, &keys("enter")),
+            Lookup::Action(Action::ConfirmYes)
+        );
+        assert!(keybindings.is_custom(Scope::Editor, keys("ctrl-k")[0]));
+        assert!(!keybindings.is_custom(Scope::Editor, keys("ctrl-s")[0]));
+        assert!(!keybindings.is_custom(Scope::Notes, keys("ctrl-k")[0]));
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let error = |contents| Keybindings::from_toml(contents).unwrap_err().to_string();
+        assert!(error("[notes]\ng = \"notes.last\"").contains("conflicts with \"g g\""));
+        assert!(
+            error("[notes]\n\"ctrl-s\" = \"note.new\"\n\"C-s\" = \"note.delete\"")
+                .contains("are the same key")
+        );
+        assert!(error("[notes]\nx = \"note.nope\"").contains("Unknown action"));
+        assert!(error("[notes]\nx = \"editor.save\"").contains("can't be bound in [notes]"));
+        assert!(error("[find]\nx = \"find.replace\"").contains("can't be bound in [find]"));
+        assert!(error("[main]\nx = \"note.new\"").contains("Unknown section [main]"));
+        assert!(error("[notes]\n\"hyper-x\" = \"note.new\"").contains("Invalid key"));
+    }
+}
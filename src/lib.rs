@@ -0,0 +1,19 @@
+pub mod app;
+pub mod clipboard;
+pub mod config;
+pub mod event;
+pub mod fuzzy;
+pub mod graphemes;
+pub mod highlight;
+pub mod keybindings;
+pub mod note;
+pub mod notebook;
+pub mod preview;
+pub mod search;
+pub mod storage;
+pub mod tags;
+pub mod tasks;
+pub mod textbox;
+pub mod tui;
+pub mod ui;
+pub mod update;
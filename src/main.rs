@@ -1,23 +1,23 @@
-pub mod app;
-pub mod event;
-pub mod note;
-pub mod textbox;
-pub mod tui;
-pub mod ui;
-pub mod update;
-
 use anyhow::Result;
-use app::AppState;
 use crossterm::terminal;
-use event::{EventHandler, EventType};
-
-use note::NoteList;
+use noted::{
+    app::AppState,
+    clipboard,
+    config::{self, Args, Config},
+    event::{EventHandler, EventType},
+    keybindings::Keybindings,
+    tui::Tui,
+    update::{paste, update},
+};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use tui::Tui;
-use update::update;
-
 fn main() -> Result<()> {
+    let args = Args::parse(std::env::args())?;
+    let config = Config::load()?;
+    let keybindings = Keybindings::load()?;
+    let db_path = config::resolve_db_path(args.db, config::db_path_from_env(), &config)?;
+    config::ensure_parent_dir(&db_path)?;
+
     let backend = CrosstermBackend::new(std::io::stderr());
     let terminal = Terminal::new(backend)?;
     let event_handler = EventHandler::new(250);
@@ -26,9 +26,10 @@ fn main() -> Result<()> {
     let height = terminal::size()
         .map(|(_, height)| height)
         .expect("Couldn't open terminal") as usize;
-    
-    let mut app = AppState::new(height);
-    app.notes = NoteList::load("./notes/test.json").unwrap_or(NoteList::new());
+
+    let mut app = AppState::new(height, db_path, config);
+    app.keybindings = keybindings;
+    app.load_notes();
     tui.enter()?;
 
     while !app.should_quit {
@@ -36,9 +37,21 @@ fn main() -> Result<()> {
 
         match tui.events.next()? {
             EventType::Tick => {}
-            EventType::Key(key_event) => update(&mut app, key_event),
+            EventType::Key(key_event) => {
+                update(&mut app, key_event);
+                if let Some(text) = app.clipboard.take() {
+                    if app.config.osc52 {
+                        let _ = tui.set_clipboard(&text);
+                    }
+                    if app.config.clipboard_tools {
+                        clipboard::copy_with_tools(&text);
+                    }
+                }
+            }
+            EventType::Paste(text) => paste(&mut app, &text),
             EventType::Mouse(_) => {}
             EventType::Resize(_, _) => {}
+            EventType::FocusGained | EventType::FocusLost => {}
         };
     }
 
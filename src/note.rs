@@ -1,20 +1,70 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
+    error::Error,
     fmt::{self, Display, Formatter},
     fs::File,
-    io::Read,
+    io::{self, Read},
+    path::{Path, PathBuf},
 };
+use uuid::Uuid;
 
-// TODO: make this configurable from app.rs
-pub const DB_PATH: &str = "../notes/notes.json";
+use crate::{notebook, storage, tags};
+
+// persistent note identifier, notes from older files get the nil id until
+// `NoteList::load` assigns them a real one
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct NoteId(Uuid);
+
+impl NoteId {
+    pub fn new() -> Self {
+        NoteId(Uuid::new_v4())
+    }
+
+    pub fn is_nil(&self) -> bool {
+        self.0.is_nil()
+    }
+
+    // first block of the uuid, enough to tell notes apart in the table
+    pub fn short(&self) -> String {
+        self.0.simple().to_string()[..8].to_string()
+    }
+}
+
+impl Display for NoteId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Note {
+    #[serde(default)]
+    pub id: NoteId,
     pub title: String,
     pub content: Vec<String>,
     pub created_at: DateTime<Utc>,
+    // None until the note is edited for the first time
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub edits: u64,
+    #[serde(default)]
+    pub word_count: usize,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // `#tags` found in the content, kept up to date with it
+    #[serde(skip)]
+    pub inline_tags: Vec<String>,
+    // notebook path, empty for notes from before notebooks existed
+    #[serde(default)]
+    pub notebook: String,
+    // set while the note sits in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Display for Note {
@@ -26,45 +76,323 @@ impl Display for Note {
 impl Note {
     pub fn new(title: &str, content: Vec<String>) -> Self {
         Note {
+            id: NoteId::new(),
             title: String::from(title),
-            content: content,
+            word_count: count_words(&content),
+            inline_tags: tags::extract_tags(&content),
+            content,
             created_at: Utc::now(),
+            updated_at: None,
+            edits: 0,
+            tags: Vec::new(),
+            notebook: notebook::DEFAULT_NOTEBOOK.to_string(),
+            deleted_at: None,
         }
     }
 
     pub fn set_title(&mut self, title: &str) {
-        self.title = String::from(title)
+        if self.title != title {
+            self.title = String::from(title);
+            self.touch();
+        }
     }
 
     pub fn set_content(&mut self, content: Vec<String>) {
-        self.content = content
+        if self.content != content {
+            self.content = content;
+            self.inline_tags = tags::extract_tags(&self.content);
+            self.touch();
+        }
     }
 
     pub fn update(&mut self, title: &str, content: Vec<String>) {
-        self.set_title(title);
-        self.set_content(content);
+        if self.title != title || self.content != content {
+            self.title = String::from(title);
+            self.content = content;
+            self.inline_tags = tags::extract_tags(&self.content);
+            self.touch();
+        }
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        if self.tags != tags {
+            self.tags = tags;
+            self.touch();
+        }
+    }
+
+    // explicit tags followed by `#tags` found in the content
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut all = self.tags.clone();
+        for tag in &self.inline_tags {
+            if !all.contains(tag) {
+                all.push(tag.clone());
+            }
+        }
+        all
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().chain(&self.inline_tags).any(|t| t == tag)
+    }
+
+    // time of the last edit, or creation if the note was never edited
+    pub fn modified_at(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.created_at)
+    }
+
+    fn touch(&mut self) {
+        self.updated_at = Some(Utc::now());
+        self.edits += 1;
+        self.word_count = count_words(&self.content);
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+fn count_words(content: &[String]) -> usize {
+    content
+        .iter()
+        .map(|line| line.split_whitespace().count())
+        .sum()
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    // there is no database yet, e.g. on first run
+    NotFound(PathBuf),
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // the database exists but is broken
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl LoadError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, LoadError::NotFound(_))
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NotFound(path) => write!(f, "No notes database at {}", path.display()),
+            LoadError::Io { path, source } => {
+                write!(f, "Couldn't read {}: {}", path.display(), source)
+            }
+            LoadError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Couldn't parse {} at line {}, column {}: {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
 pub struct NoteList {
     notes: Vec<Note>,
+    // every notebook path, including empty ones and all parents
+    #[serde(default)]
+    notebooks: Vec<String>,
+    // deleted notes, newest last, until they are restored or purged
+    #[serde(default)]
+    trash: Vec<Note>,
+    // set when `load` had to migrate the file, so the caller knows to save
+    #[serde(skip)]
+    migrated: bool,
 }
 
 impl NoteList {
     pub fn new() -> Self {
-        NoteList { notes: Vec::new() }
+        NoteList {
+            notes: Vec::new(),
+            notebooks: Vec::new(),
+            trash: Vec::new(),
+            migrated: false,
+        }
     }
 
     pub fn insert(&mut self, note: &Note) {
+        self.add_notebook(&note.notebook);
         self.notes.push(note.clone());
     }
 
-    pub fn remove(&mut self, id: usize) -> Option<Note> {
-        if id < self.notes.len() {
-            Some(self.notes.remove(id))
-        } else {
-            None
+    pub fn remove(&mut self, id: NoteId) -> Option<Note> {
+        self.position(id).map(|idx| self.notes.remove(idx))
+    }
+
+    // move a note to the trash, returns false if there is no such note
+    pub fn trash(&mut self, id: NoteId) -> bool {
+        match self.remove(id) {
+            Some(mut note) => {
+                note.deleted_at = Some(Utc::now());
+                self.trash.push(note);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // put a trashed note back into its notebook
+    pub fn restore(&mut self, id: NoteId) -> bool {
+        match self.trash.iter().position(|note| note.id == id) {
+            Some(idx) => {
+                let mut note = self.trash.remove(idx);
+                note.deleted_at = None;
+                self.insert(&note);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // permanently delete a note from the trash
+    pub fn purge(&mut self, id: NoteId) -> Option<Note> {
+        let idx = self.trash.iter().position(|note| note.id == id)?;
+        Some(self.trash.remove(idx))
+    }
+
+    pub fn empty_trash(&mut self) -> usize {
+        let purged = self.trash.len();
+        self.trash.clear();
+        purged
+    }
+
+    // drop trashed notes deleted more than `retention` before `now`
+    pub fn purge_expired(&mut self, retention: Duration, now: DateTime<Utc>) -> usize {
+        let before = self.trash.len();
+        self.trash.retain(|note| match note.deleted_at {
+            Some(deleted_at) => now - deleted_at < retention,
+            None => true,
+        });
+        before - self.trash.len()
+    }
+
+    pub fn trashed(&self) -> impl DoubleEndedIterator<Item = &Note> {
+        self.trash.iter()
+    }
+
+    // index of a note in display order
+    pub fn position(&self, id: NoteId) -> Option<usize> {
+        self.notes.iter().position(|note| note.id == id)
+    }
+
+    pub fn id_at(&self, idx: usize) -> Option<NoteId> {
+        self.notes.get(idx).map(|note| note.id)
+    }
+
+    pub fn was_migrated(&self) -> bool {
+        self.migrated
+    }
+
+    // give notes without an id (or with a duplicated one) a fresh id
+    fn assign_ids(&mut self) -> bool {
+        let mut seen = HashSet::new();
+        let mut changed = false;
+        for note in self.notes.iter_mut().chain(self.trash.iter_mut()) {
+            if note.id.is_nil() || !seen.insert(note.id) {
+                note.id = NoteId::new();
+                seen.insert(note.id);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // put notes from flat files into the default notebook and make sure
+    // every notebook a note refers to is known
+    fn assign_notebooks(&mut self) -> bool {
+        let mut changed = false;
+        for note in self.notes.iter_mut() {
+            let path = notebook::normalize(&note.notebook)
+                .unwrap_or_else(|| notebook::DEFAULT_NOTEBOOK.to_string());
+            if note.notebook != path {
+                note.notebook = path;
+                changed = true;
+            }
+        }
+
+        let paths: Vec<String> = self
+            .notes
+            .iter()
+            .map(|note| note.notebook.clone())
+            .collect();
+        for path in paths {
+            changed |= self.add_notebook(&path);
+        }
+        changed
+    }
+
+    pub fn notebooks(&self) -> &[String] {
+        &self.notebooks
+    }
+
+    // add a notebook and its parents, returns true if anything was added
+    pub fn add_notebook(&mut self, path: &str) -> bool {
+        let mut added = false;
+        for path in notebook::with_ancestors(path) {
+            if !self.notebooks.contains(&path) {
+                self.notebooks.push(path);
+                added = true;
+            }
+        }
+        self.notebooks.sort_by(|a, b| notebook::tree_order(a, b));
+        added
+    }
+
+    // rename or move a notebook along with everything nested inside it
+    pub fn move_notebook(&mut self, from: &str, to: &str) -> Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        if notebook::is_within(to, from) {
+            anyhow::bail!("Can't move notebook {} into itself", from);
+        }
+        if self.notebooks.iter().any(|path| path == to) {
+            anyhow::bail!("Notebook {} already exists", to);
+        }
+
+        for path in self.notebooks.iter_mut() {
+            if notebook::is_within(path, from) {
+                *path = notebook::rebase(path, from, to);
+            }
+        }
+        for note in self.notes.iter_mut() {
+            if notebook::is_within(&note.notebook, from) {
+                note.notebook = notebook::rebase(&note.notebook, from, to);
+            }
+        }
+        self.add_notebook(to);
+        Ok(())
+    }
+
+    pub fn move_note(&mut self, id: NoteId, path: &str) {
+        self.add_notebook(path);
+        if let Some(note) = self.get(id) {
+            note.notebook = path.to_string();
         }
     }
 
@@ -76,17 +404,52 @@ impl NoteList {
         self.notes.len()
     }
 
-    pub fn save(&self, file_path: &str) -> Result<()> {
-        let file = File::create(file_path)?;
-        let _ = serde_json::to_writer_pretty(file, self)?;
-        Ok(())
+    // atomically replace the file, leaving its backups as they are
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<()> {
+        self.write(file_path.as_ref(), None)
+    }
+
+    // atomically replace the file, keeping up to `backups` previous versions
+    pub fn save_with_backups<P: AsRef<Path>>(&self, file_path: P, backups: usize) -> Result<()> {
+        self.write(file_path.as_ref(), Some(backups))
     }
 
-    pub fn load(file_path: &str) -> Result<Self> {
-        let mut file = File::open(file_path).expect("File does not exist");
+    fn write(&self, path: &Path, backups: Option<usize>) -> Result<()> {
+        storage::atomic_write(path, backups, |writer| {
+            serde_json::to_writer_pretty(writer, self)?;
+            Ok(())
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Self, LoadError> {
+        let path = file_path.as_ref();
+        let io_error = |source: io::Error| match source.kind() {
+            io::ErrorKind::NotFound => LoadError::NotFound(path.to_path_buf()),
+            _ => LoadError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        };
+
+        let mut file = File::open(path).map_err(io_error)?;
         let mut json_string = String::new();
-        let _ = file.read_to_string(&mut json_string).expect("Couldn't read from JSON");
-        let note_list: NoteList = serde_json::from_str(&json_string).expect("Couldn't load JSON into NoteList");
+        file.read_to_string(&mut json_string).map_err(io_error)?;
+        let mut note_list: NoteList =
+            serde_json::from_str(&json_string).map_err(|e| LoadError::Parse {
+                path: path.to_path_buf(),
+                line: e.line(),
+                column: e.column(),
+                message: e.to_string(),
+            })?;
+        note_list.migrated = note_list.assign_ids();
+        note_list.migrated |= note_list.assign_notebooks();
+        for note in note_list.notes.iter_mut() {
+            // files written before word counts were cached
+            if note.word_count == 0 {
+                note.word_count = count_words(&note.content);
+            }
+            note.inline_tags = tags::extract_tags(&note.content);
+        }
         Ok(note_list)
     }
 
@@ -100,8 +463,12 @@ impl NoteList {
         self.notes.iter()
     }
 
-    pub fn get(&mut self, id: usize) -> Option<&mut Note> {
-        self.notes.get_mut(id)
+    pub fn find(&self, id: NoteId) -> Option<&Note> {
+        self.notes.iter().find(|note| note.id == id)
+    }
+
+    pub fn get(&mut self, id: NoteId) -> Option<&mut Note> {
+        self.notes.iter_mut().find(|note| note.id == id)
     }
 }
 
@@ -123,14 +490,22 @@ mod tests {
     #[test]
     fn test_note_deleted() {
         let note = Note {
+            id: NoteId::new(),
             title: "title".into(),
             content: vec!["This".into(), "is".into(), "the".into(), "content".into()],
             created_at: Utc::now(),
+            updated_at: None,
+            edits: 0,
+            word_count: 4,
+            tags: Vec::new(),
+            inline_tags: Vec::new(),
+            notebook: notebook::DEFAULT_NOTEBOOK.into(),
+            deleted_at: None,
         };
 
         let mut note_list = NoteList::new();
         note_list.insert(&note);
-        note_list.remove(0);
+        note_list.remove(note.id);
         assert_eq!(note_list.length(), 0)
     }
 
@@ -146,34 +521,262 @@ mod tests {
         assert_eq!(note_list.length(), 2);
     }
 
-
     #[test]
     fn test_write_notelist_to_json() {
         let mut note_list = NoteList::new();
         note_list.insert(&Note::new("title1", vec!["content1".to_string()]));
         note_list.insert(&Note::new("title2", vec!["content2".to_string()]));
 
-        let file_path = "test.json";
-        let result = note_list.save(file_path);
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("test.json");
+        let result = note_list.save(&file_path);
         assert!(result.is_ok());
-        assert!(fs::metadata(file_path).is_ok());
+        assert!(fs::metadata(&file_path).is_ok());
     }
 
     #[test]
     fn test_load_notelist_from_json() {
-        let test_file = "test.json";
+        let dir = tempfile::tempdir().unwrap();
+        let test_file = dir.path().join("test.json");
         let mut nl = NoteList::new();
         nl.insert(&Note::new("title1", vec!["content1".into()]));
         nl.insert(&Note::new("title2", vec!["content2".into()]));
 
-        let _ = nl.save(test_file);
+        let _ = nl.save(&test_file);
 
-        let result = NoteList::load(test_file);
+        let result = NoteList::load(&test_file);
         // assert!(result.is_ok());
 
         assert_eq!(nl, result.unwrap());
-        fs::remove_file(test_file).unwrap();
+        fs::remove_file(&test_file).unwrap();
         // let note_list = result.unwrap();
         // assert_eq!(note_list.length(), 2);
     }
+
+    #[test]
+    fn test_save_keeps_existing_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        let note_list = NoteList::new();
+        note_list.save_with_backups(&path, 1).unwrap();
+        note_list.save_with_backups(&path, 1).unwrap();
+        assert!(storage::backup_path(&path, 1).exists());
+
+        note_list.save(&path).unwrap();
+        assert!(storage::backup_path(&path, 1).exists());
+    }
+
+    #[test]
+    fn test_inline_tags_follow_the_content() {
+        let mut note = Note::new("", vec!["#one".into()]);
+        assert!(note.has_tag("one"));
+        note.set_content(vec!["#two".into()]);
+        assert!(!note.has_tag("one") && note.has_tag("two"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        let mut note_list = NoteList::new();
+        note_list.insert(&note);
+        note_list.save(&path).unwrap();
+        let loaded = NoteList::load(&path).unwrap();
+        assert_eq!(loaded.find(note.id).unwrap().all_tags(), vec!["two"]);
+    }
+
+    #[test]
+    fn test_lookup_by_id_survives_removal() {
+        let first = Note::new("first", Vec::new());
+        let second = Note::new("second", Vec::new());
+
+        let mut note_list = NoteList::new();
+        note_list.insert(&first);
+        note_list.insert(&second);
+        note_list.remove(first.id);
+
+        assert_eq!(note_list.get(second.id).unwrap().title, "second");
+        assert!(note_list.get(first.id).is_none());
+        assert_eq!(note_list.position(second.id), Some(0));
+    }
+
+    #[test]
+    fn test_load_assigns_missing_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(
+            &path,
+            r#"{"notes": [
+                {"title": "a", "content": [], "created_at": "2023-11-20T10:00:00Z"},
+                {"title": "b", "content": [], "created_at": "2023-11-20T10:00:00Z"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let note_list = NoteList::load(&path).unwrap();
+        let ids: Vec<NoteId> = note_list.iter().map(|note| note.id).collect();
+        assert!(note_list.was_migrated());
+        assert!(ids.iter().all(|id| !id.is_nil()));
+        assert_ne!(ids[0], ids[1]);
+
+        note_list.save(&path).unwrap();
+        let reloaded = NoteList::load(&path).unwrap();
+        assert!(!reloaded.was_migrated());
+        assert_eq!(reloaded.id_at(0), Some(ids[0]));
+    }
+
+    #[test]
+    fn test_edit_metadata_only_changes_on_edit() {
+        let mut note = Note::new("title", vec!["one two".into()]);
+        assert_eq!(note.word_count, 2);
+        assert!(note.updated_at.is_none());
+
+        note.set_content(vec!["one two".into()]);
+        note.set_title("title");
+        assert_eq!(note.edits, 0);
+        assert!(note.updated_at.is_none());
+
+        note.set_content(vec!["one two".into(), "three".into()]);
+        assert_eq!(note.edits, 1);
+        assert_eq!(note.word_count, 3);
+        assert!(note.updated_at.is_some());
+
+        note.update("new title", vec!["four".into()]);
+        assert_eq!(note.edits, 2);
+        assert_eq!(note.word_count, 1);
+    }
+
+    #[test]
+    fn test_load_old_file_without_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(
+            &path,
+            r#"{"notes": [
+                {"title": "a", "content": ["x y z"], "created_at": "2023-11-20T10:00:00Z"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let note_list = NoteList::load(&path).unwrap();
+        let note = note_list.iter().next().unwrap();
+        assert_eq!(note.updated_at, None);
+        assert_eq!(note.modified_at(), note.created_at);
+        assert_eq!(note.edits, 0);
+        assert_eq!(note.word_count, 3);
+    }
+
+    #[test]
+    fn test_flat_file_migrates_into_default_notebook() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(
+            &path,
+            r#"{"notes": [
+                {"title": "a", "content": [], "created_at": "2023-11-20T10:00:00Z"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let note_list = NoteList::load(&path).unwrap();
+        assert!(note_list.was_migrated());
+        assert_eq!(
+            note_list.notebooks(),
+            &[notebook::DEFAULT_NOTEBOOK.to_string()]
+        );
+        assert_eq!(
+            note_list.iter().next().unwrap().notebook,
+            notebook::DEFAULT_NOTEBOOK
+        );
+    }
+
+    #[test]
+    fn test_move_notebook_moves_notes() {
+        let mut note = Note::new("acme", Vec::new());
+        note.notebook = "work/clients/acme".into();
+
+        let mut note_list = NoteList::new();
+        note_list.insert(&note);
+        note_list.add_notebook("archive");
+
+        note_list.move_notebook("work", "archive/work").unwrap();
+        assert_eq!(
+            note_list.find(note.id).unwrap().notebook,
+            "archive/work/clients/acme"
+        );
+        assert!(!note_list
+            .notebooks()
+            .iter()
+            .any(|path| path.starts_with("work")));
+
+        assert!(note_list
+            .move_notebook("archive", "archive/nested")
+            .is_err());
+        assert!(note_list.move_notebook("archive/work", "archive").is_err());
+
+        note_list.move_note(note.id, "inbox");
+        assert_eq!(note_list.find(note.id).unwrap().notebook, "inbox");
+        assert!(note_list.notebooks().contains(&"inbox".to_string()));
+    }
+
+    #[test]
+    fn test_trash_and_restore() {
+        let mut note = Note::new("title", Vec::new());
+        note.notebook = "work".into();
+
+        let mut note_list = NoteList::new();
+        note_list.insert(&note);
+        assert!(note_list.trash(note.id));
+        assert!(note_list.is_empty());
+
+        let trashed = note_list.trashed().next().unwrap();
+        assert_eq!(trashed.id, note.id);
+        assert!(trashed.deleted_at.is_some());
+
+        assert!(note_list.restore(note.id));
+        assert_eq!(note_list.find(note.id).unwrap().deleted_at, None);
+        assert_eq!(note_list.find(note.id).unwrap().notebook, "work");
+        assert_eq!(note_list.trashed().count(), 0);
+    }
+
+    #[test]
+    fn test_purge_expired_trash() {
+        let old = Note::new("old", Vec::new());
+        let recent = Note::new("recent", Vec::new());
+
+        let mut note_list = NoteList::new();
+        note_list.insert(&old);
+        note_list.insert(&recent);
+        note_list.trash(old.id);
+        note_list.trash(recent.id);
+
+        let now = Utc::now();
+        note_list.trash[0].deleted_at = Some(now - Duration::days(40));
+
+        assert_eq!(note_list.purge_expired(Duration::days(30), now), 1);
+        let left: Vec<NoteId> = note_list.trashed().map(|note| note.id).collect();
+        assert_eq!(left, vec![recent.id]);
+
+        assert!(note_list.purge(recent.id).is_some());
+        assert_eq!(note_list.trashed().count(), 0);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = NoteList::load(dir.path().join("missing.json"));
+        assert!(result.unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(&path, "{\n  \"notes\": [\n    {\"title\": }\n").unwrap();
+
+        match NoteList::load(&path) {
+            Err(LoadError::Parse { line, column, .. }) => {
+                assert_eq!(line, 3);
+                assert_eq!(column, 15);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
 }
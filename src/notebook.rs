@@ -0,0 +1,177 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+// notebooks are stored as slash separated paths, e.g. `work/clients/acme`
+pub const DEFAULT_NOTEBOOK: &str = "default";
+const SEPARATOR: char = '/';
+
+// clean up user input like " /work//clients/ " into "work/clients"
+pub fn normalize(input: &str) -> Option<String> {
+    let segments: Vec<&str> = input
+        .split(SEPARATOR)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments.join("/"))
+    }
+}
+
+pub fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once(SEPARATOR).map(|(parent, _)| parent)
+}
+
+pub fn name(path: &str) -> &str {
+    path.rsplit_once(SEPARATOR)
+        .map(|(_, name)| name)
+        .unwrap_or(path)
+}
+
+pub fn depth(path: &str) -> usize {
+    path.matches(SEPARATOR).count()
+}
+
+// true if `path` is `ancestor` itself or nested somewhere below it
+pub fn is_within(path: &str, ancestor: &str) -> bool {
+    path == ancestor
+        || (path.starts_with(ancestor) && path[ancestor.len()..].starts_with(SEPARATOR))
+}
+
+// the path itself followed by all of its parents
+pub fn with_ancestors(path: &str) -> Vec<String> {
+    let mut paths = vec![path.to_string()];
+    let mut current = path;
+    while let Some(parent) = parent(current) {
+        paths.push(parent.to_string());
+        current = parent;
+    }
+    paths
+}
+
+// replace the `from` prefix of `path` with `to`
+pub fn rebase(path: &str, from: &str, to: &str) -> String {
+    format!("{}{}", to, &path[from.len()..])
+}
+
+// order paths segment by segment so children follow their parent directly,
+// a plain string sort would put `work-old` between `work` and `work/clients`
+pub fn tree_order(a: &str, b: &str) -> Ordering {
+    a.split(SEPARATOR).cmp(b.split(SEPARATOR))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeRow {
+    // None is the "All notes" root
+    pub path: Option<String>,
+    pub depth: usize,
+    pub has_children: bool,
+    pub collapsed: bool,
+}
+
+impl TreeRow {
+    pub fn label(&self) -> &str {
+        match &self.path {
+            Some(path) => name(path),
+            None => "All notes",
+        }
+    }
+}
+
+// flatten the notebook paths into the rows shown in the sidebar, skipping
+// anything below a collapsed notebook
+pub fn tree_rows(notebooks: &[String], collapsed: &HashSet<String>) -> Vec<TreeRow> {
+    let mut sorted: Vec<&String> = notebooks.iter().collect();
+    sorted.sort_by(|a, b| tree_order(a, b));
+
+    let mut rows = vec![TreeRow {
+        path: None,
+        depth: 0,
+        has_children: !sorted.is_empty(),
+        collapsed: false,
+    }];
+
+    for (i, path) in sorted.iter().enumerate() {
+        let hidden = with_ancestors(path)
+            .iter()
+            .skip(1)
+            .any(|ancestor| collapsed.contains(ancestor));
+        if hidden {
+            continue;
+        }
+
+        let has_children = sorted
+            .get(i + 1)
+            .is_some_and(|next| is_within(next, path) && *next != *path);
+        rows.push(TreeRow {
+            path: Some(path.to_string()),
+            depth: depth(path) + 1,
+            has_children,
+            collapsed: collapsed.contains(path.as_str()),
+        });
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" /work//clients/ "), Some("work/clients".into()));
+        assert_eq!(normalize(" / "), None);
+    }
+
+    #[test]
+    fn test_path_helpers() {
+        assert_eq!(parent("work/clients/acme"), Some("work/clients"));
+        assert_eq!(parent("work"), None);
+        assert_eq!(name("work/clients/acme"), "acme");
+        assert!(is_within("work/clients", "work"));
+        assert!(!is_within("workshop", "work"));
+        assert_eq!(
+            with_ancestors("a/b/c"),
+            vec!["a/b/c".to_string(), "a/b".into(), "a".into()]
+        );
+        assert_eq!(
+            rebase("work/clients/acme", "work", "archive/work"),
+            "archive/work/clients/acme"
+        );
+    }
+
+    #[test]
+    fn test_tree_rows_respect_collapsed() {
+        let notebooks = vec![
+            "work/clients".to_string(),
+            "default".into(),
+            "work".into(),
+            "work-old".into(),
+            "work/clients/acme".into(),
+        ];
+
+        let rows = tree_rows(&notebooks, &HashSet::new());
+        let labels: Vec<&str> = rows.iter().map(|row| row.label()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "All notes",
+                "default",
+                "work",
+                "clients",
+                "acme",
+                "work-old"
+            ]
+        );
+        assert!(rows[2].has_children);
+        assert!(!rows[1].has_children);
+        assert_eq!(rows[4].depth, 3);
+
+        let collapsed = HashSet::from(["work".to_string()]);
+        let rows = tree_rows(&notebooks, &collapsed);
+        assert_eq!(rows.len(), 4);
+        assert!(rows[2].collapsed);
+    }
+}
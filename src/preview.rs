@@ -0,0 +1,741 @@
+use std::{collections::HashMap, iter, mem, ops::Range};
+
+use pulldown_cmark::{
+    Alignment, CodeBlockKind, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd,
+};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::highlight::{self, CodeState};
+
+// A note's Markdown rendered as styled lines for the preview pane. Text is
+// wrapped here rather than by the widget, so every line is one screen row
+// and knows the note row it came from, which is how the preview keeps to the
+// same place as the editor.
+
+// styles for the byte ranges of a line
+type Styles = Vec<(Range<usize>, Style)>;
+
+#[derive(Debug, Default)]
+pub struct Preview {
+    pub lines: Vec<Line<'static>>,
+    // the note row each line was rendered from, never decreasing
+    pub rows: Vec<usize>,
+    // the lines of each code block as highlighted, by language and text
+    highlighted: HashMap<(String, String), Vec<Styles>>,
+}
+
+impl Preview {
+    pub fn render(text: &str, width: usize) -> Self {
+        let mut preview = Preview::default();
+        preview.update(text, width);
+        preview
+    }
+
+    // render the note again, code blocks that haven't changed keep their
+    // highlighting rather than being run through their grammar again
+    pub fn update(&mut self, text: &str, width: usize) {
+        let options = Options::ENABLE_TABLES
+            | Options::ENABLE_STRIKETHROUGH
+            | Options::ENABLE_TASKLISTS
+            | Options::ENABLE_FOOTNOTES;
+        let starts: Vec<usize> = iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        let mut writer = Writer::new(width.max(1));
+        writer.highlighted = mem::take(&mut self.highlighted);
+        for (event, range) in Parser::new_ext(text, options).into_offset_iter() {
+            writer.row = starts.partition_point(|&start| start <= range.start) - 1;
+            writer.event(event);
+        }
+        writer.flush();
+        *self = Preview {
+            lines: writer.lines,
+            rows: writer.rows,
+            highlighted: writer.kept,
+        };
+    }
+
+    // the first line of the block a note row is part of
+    pub fn line_at(&self, row: usize) -> usize {
+        match self.rows.partition_point(|&from| from <= row) {
+            0 => 0,
+            after => {
+                let block = self.rows[after - 1];
+                self.rows.partition_point(|&from| from < block)
+            }
+        }
+    }
+
+    pub fn row_at(&self, line: usize) -> usize {
+        self.rows
+            .get(line)
+            .or(self.rows.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+fn heading(level: HeadingLevel) -> Style {
+    match level {
+        HeadingLevel::H1 => highlight::heading().add_modifier(Modifier::UNDERLINED),
+        HeadingLevel::H2 => highlight::heading(),
+        _ => Style::default().add_modifier(Modifier::BOLD),
+    }
+}
+
+// what the lines inside a block start with, like a quote bar or a bullet
+struct Indent {
+    first: Span<'static>,
+    rest: Span<'static>,
+    started: bool,
+}
+
+impl Indent {
+    fn new(first: Span<'static>) -> Self {
+        let rest = Span::raw(" ".repeat(first.width()));
+        Indent {
+            first,
+            rest,
+            started: false,
+        }
+    }
+}
+
+// a code block is written out once all of it is read, to highlight it whole
+#[derive(Default)]
+struct CodeBlock {
+    lang: String,
+    // the note row of its first line
+    row: Option<usize>,
+    text: String,
+}
+
+#[derive(Default)]
+struct Table {
+    alignments: Vec<Alignment>,
+    // each row's note row and cells, the header first
+    rows: Vec<(usize, Vec<Vec<Span<'static>>>)>,
+}
+
+#[derive(Default)]
+struct Writer {
+    width: usize,
+    lines: Vec<Line<'static>>,
+    rows: Vec<usize>,
+    // the note row of the event being written
+    row: usize,
+    // the line being written and the row it started on
+    spans: Vec<Span<'static>>,
+    line_row: Option<usize>,
+    indents: Vec<Indent>,
+    // inline styles, innermost last
+    styles: Vec<Style>,
+    // the next number of each ordered list, None for bullets
+    lists: Vec<Option<u64>>,
+    // the url shown after a link's text, None when the text is the url
+    links: Vec<Option<String>>,
+    code: Option<CodeBlock>,
+    // highlighted code blocks from the last render, and those used in this one
+    highlighted: HashMap<(String, String), Vec<Styles>>,
+    kept: HashMap<(String, String), Vec<Styles>>,
+    table: Option<Table>,
+    // a blank line goes before the next block
+    gap: bool,
+}
+
+impl Writer {
+    fn new(width: usize) -> Self {
+        Writer {
+            width,
+            ..Default::default()
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.code.is_some() => {
+                if let Some(code) = self.code.as_mut() {
+                    code.row.get_or_insert(self.row);
+                    code.text.push_str(&text);
+                }
+            }
+            Event::Text(text) => self.push(&text, self.style()),
+            Event::Code(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.push(&text, self.style().patch(highlight::code()))
+            }
+            // html blocks come a line at a time
+            Event::Html(html) => {
+                self.push(html.trim_end(), highlight::marker());
+                self.flush();
+            }
+            Event::InlineHtml(html) => self.push(&html, highlight::marker()),
+            Event::FootnoteReference(label) => {
+                self.push(&format!("[^{label}]"), highlight::marker())
+            }
+            Event::SoftBreak => self.push(" ", self.style()),
+            Event::HardBreak => self.flush(),
+            Event::Rule => {
+                self.block();
+                let width = self.width.saturating_sub(self.indent_width());
+                self.push(&"─".repeat(width), highlight::marker());
+                self.flush();
+                self.gap = true;
+            }
+            Event::TaskListMarker(done) => {
+                let checkbox = if done { "[x] " } else { "[ ] " };
+                self.push(checkbox, Style::default().fg(Color::Green))
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => self.block(),
+            Tag::Heading { level, .. } => {
+                self.block();
+                self.styles.push(heading(level));
+            }
+            Tag::BlockQuote(_) => {
+                self.block();
+                let bar = Span::styled("│ ", highlight::marker());
+                self.indents.push(Indent {
+                    first: bar.clone(),
+                    rest: bar,
+                    started: false,
+                });
+                self.styles
+                    .push(Style::default().add_modifier(Modifier::ITALIC));
+            }
+            Tag::CodeBlock(kind) => {
+                self.block();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some(CodeBlock {
+                    lang,
+                    ..Default::default()
+                });
+                self.indents.push(Indent::new(Span::raw("  ")));
+            }
+            Tag::List(start) => {
+                self.block();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.block();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => ["• ", "◦ ", "▪ "][(self.lists.len() + 2) % 3].to_string(),
+                };
+                self.indents
+                    .push(Indent::new(Span::styled(bullet, highlight::bullet())));
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.block();
+                let label = Span::styled(format!("[^{label}]: "), highlight::marker());
+                self.indents.push(Indent::new(label));
+            }
+            Tag::Table(alignments) => {
+                self.block();
+                self.table = Some(Table {
+                    alignments,
+                    rows: Vec::new(),
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push((self.row, Vec::new()));
+                }
+            }
+            Tag::Emphasis => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                self.styles.push(highlight::link());
+                let shown = !matches!(link_type, LinkType::Autolink | LinkType::Email);
+                self.links
+                    .push((shown && !dest_url.is_empty()).then(|| dest_url.to_string()));
+            }
+            Tag::Image { .. } => {
+                self.styles.push(highlight::marker());
+                self.push("[image: ", highlight::marker());
+            }
+            Tag::Superscript | Tag::Subscript => self.styles.push(Style::default()),
+            Tag::TableCell
+            | Tag::DefinitionList
+            | Tag::DefinitionListTitle
+            | Tag::DefinitionListDefinition
+            | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.end_block(),
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.end_block();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.styles.pop();
+                self.indents.pop();
+                self.gap = true;
+            }
+            TagEnd::CodeBlock => {
+                self.flush();
+                if let Some(code) = self.code.take() {
+                    self.write_code(code);
+                }
+                self.indents.pop();
+                self.gap = true;
+            }
+            // lists nested in a list item don't stand apart from it
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                self.gap |= self.lists.is_empty();
+            }
+            TagEnd::Item => {
+                self.flush();
+                // an empty item still shows its bullet
+                if self.indents.last().is_some_and(|indent| !indent.started) {
+                    self.line_row.get_or_insert(self.row);
+                    self.emit();
+                }
+                self.indents.pop();
+            }
+            TagEnd::FootnoteDefinition => {
+                self.flush();
+                self.indents.pop();
+                self.gap = true;
+            }
+            TagEnd::TableCell => {
+                let cell = mem::take(&mut self.spans);
+                self.line_row = None;
+                if let Some((_, cells)) =
+                    self.table.as_mut().and_then(|table| table.rows.last_mut())
+                {
+                    cells.push(cell);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.write_table(table);
+                }
+                self.gap = true;
+            }
+            TagEnd::Link => {
+                self.styles.pop();
+                if let Some(Some(url)) = self.links.pop() {
+                    self.push(&format!(" ({url})"), highlight::marker());
+                }
+            }
+            TagEnd::Image => {
+                self.push("]", highlight::marker());
+                self.styles.pop();
+            }
+            TagEnd::Emphasis
+            | TagEnd::Strong
+            | TagEnd::Strikethrough
+            | TagEnd::Superscript
+            | TagEnd::Subscript => {
+                self.styles.pop();
+            }
+            TagEnd::TableHead
+            | TagEnd::TableRow
+            | TagEnd::DefinitionList
+            | TagEnd::DefinitionListTitle
+            | TagEnd::DefinitionListDefinition
+            | TagEnd::MetadataBlock(_) => {}
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, &inner| style.patch(inner))
+    }
+
+    fn indent_width(&self) -> usize {
+        self.indents.iter().map(|indent| indent.rest.width()).sum()
+    }
+
+    fn push(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        self.line_row.get_or_insert(self.row);
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+
+    // code keeps its lines, blank ones too
+    fn write_code(&mut self, code: CodeBlock) {
+        let key = (code.lang, code.text);
+        let styles = match self.highlighted.remove(&key) {
+            Some(styles) => styles,
+            None => match self.kept.get(&key) {
+                Some(styles) => styles.clone(),
+                None => highlight_code(&key.0, &key.1),
+            },
+        };
+
+        let row = code.row.unwrap_or(self.row);
+        for (i, (line, styles)) in key.1.lines().zip(&styles).enumerate() {
+            self.line_row = Some(row + i);
+            let mut at = 0;
+            for (range, style) in styles {
+                self.push(
+                    &line[at..range.start].replace('\t', "    "),
+                    highlight::code(),
+                );
+                self.push(&line[range.clone()].replace('\t', "    "), *style);
+                at = range.end;
+            }
+            self.push(&line[at..].replace('\t', "    "), highlight::code());
+            self.emit();
+        }
+        self.kept.insert(key, styles);
+    }
+
+    // start a block on a new line, apart from the block before it
+    fn block(&mut self) {
+        self.flush();
+        if mem::take(&mut self.gap) && !self.lines.is_empty() {
+            let prefix: Vec<Span> = self
+                .indents
+                .iter()
+                .map(|indent| indent.rest.clone())
+                .collect();
+            let row = self.rows.last().copied().unwrap_or_default();
+            self.lines.push(Line::from(prefix));
+            self.rows.push(row);
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.flush();
+        self.gap = true;
+    }
+
+    fn flush(&mut self) {
+        if !self.spans.is_empty() {
+            self.emit();
+        }
+    }
+
+    // write out the line being built, wrapped to the width left by the indents
+    fn emit(&mut self) {
+        let row = self.line_row.take().unwrap_or(self.row);
+        let width = self.width.saturating_sub(self.indent_width()).max(1);
+        let first: Vec<Span> = self
+            .indents
+            .iter_mut()
+            .map(|indent| match mem::replace(&mut indent.started, true) {
+                true => indent.rest.clone(),
+                false => indent.first.clone(),
+            })
+            .collect();
+        let rest: Vec<Span> = self
+            .indents
+            .iter()
+            .map(|indent| indent.rest.clone())
+            .collect();
+
+        let spans = mem::take(&mut self.spans);
+        for (i, line) in wrap(spans, width).into_iter().enumerate() {
+            let mut spans = match i {
+                0 => first.clone(),
+                _ => rest.clone(),
+            };
+            spans.extend(line);
+            self.lines.push(Line::from(spans));
+            self.rows.push(row);
+        }
+    }
+
+    // columns as wide as their widest cell, narrowed until the table fits
+    fn write_table(&mut self, table: Table) {
+        let columns = table.alignments.len();
+        let mut widths = vec![0; columns];
+        for (_, cells) in &table.rows {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.iter().map(Span::width).sum());
+            }
+        }
+        let fits = self
+            .width
+            .saturating_sub(self.indent_width() + 3 * columns.saturating_sub(1));
+        while widths.iter().sum::<usize>() > fits {
+            match widths.iter_mut().max() {
+                Some(widest) if *widest > 3 => *widest -= 1,
+                _ => break,
+            }
+        }
+
+        let separator = Span::styled(" │ ", highlight::marker());
+        for (i, (row, mut cells)) in table.rows.into_iter().enumerate() {
+            cells.resize(columns, Vec::new());
+            for (col, cell) in cells.into_iter().enumerate() {
+                if col > 0 {
+                    self.spans.push(separator.clone());
+                }
+                let mut cell = truncate(cell, widths[col]);
+                // the header is bold
+                if i == 0 {
+                    for span in &mut cell {
+                        span.style = span.style.add_modifier(Modifier::BOLD);
+                    }
+                }
+                let pad = widths[col] - cell.iter().map(Span::width).sum::<usize>();
+                let (left, right) = match table.alignments[col] {
+                    Alignment::Right => (pad, 0),
+                    Alignment::Center => (pad / 2, pad - pad / 2),
+                    Alignment::Left | Alignment::None => (0, pad),
+                };
+                self.spans.push(Span::raw(" ".repeat(left)));
+                self.spans.extend(cell);
+                self.spans.push(Span::raw(" ".repeat(right)));
+            }
+            self.line_row = Some(row);
+            self.emit();
+
+            if i == 0 {
+                let rule: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+                self.line_row = Some(row);
+                self.push(&rule.join("─┼─"), highlight::marker());
+                self.emit();
+            }
+        }
+    }
+}
+
+// each line of a block styled by its language's grammar, or all in the code
+// color for a language without one
+fn highlight_code(lang: &str, text: &str) -> Vec<Styles> {
+    let mut grammar = CodeState::new(lang);
+    text.lines()
+        .map(|line| match grammar.as_mut() {
+            Some(grammar) => grammar.highlight(line),
+            None => vec![(0..line.len(), highlight::code())],
+        })
+        .collect()
+}
+
+// break styled text into lines of at most `width` cells, at spaces where
+// it can. A word longer than a line is broken anywhere
+fn wrap(spans: Vec<Span<'static>>, width: usize) -> Vec<Vec<Span<'static>>> {
+    let mut lines = vec![Vec::new()];
+    let mut used = 0;
+    for span in spans {
+        for word in words(&span.content) {
+            if used > 0 && used + word.width() > width {
+                trim_end(lines.last_mut().unwrap());
+                lines.push(Vec::new());
+                used = 0;
+                if word.starts_with(' ') {
+                    continue;
+                }
+            }
+            let mut piece = String::new();
+            for grapheme in word.graphemes(true) {
+                if used > 0 && used + grapheme.width() > width {
+                    append(lines.last_mut().unwrap(), mem::take(&mut piece), span.style);
+                    lines.push(Vec::new());
+                    used = 0;
+                }
+                piece.push_str(grapheme);
+                used += grapheme.width();
+            }
+            append(lines.last_mut().unwrap(), piece, span.style);
+        }
+    }
+    lines
+}
+
+// add text to a line, to its last span when that has the same style
+fn append(line: &mut Vec<Span<'static>>, text: String, style: Style) {
+    match line.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(&text),
+        _ => line.push(Span::styled(text, style)),
+    }
+}
+
+// drop the spaces a line ends with, the space it broke at
+fn trim_end(line: &mut Vec<Span<'static>>) {
+    while let Some(last) = line.last_mut() {
+        let trimmed = last.content.trim_end_matches(' ').len();
+        last.content.to_mut().truncate(trimmed);
+        match last.content.is_empty() {
+            true => line.pop(),
+            false => break,
+        };
+    }
+}
+
+// runs of spaces and of everything else
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    iter::from_fn(move || {
+        let space = rest.chars().next()? == ' ';
+        let end = rest
+            .find(|ch: char| (ch == ' ') != space)
+            .unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        rest = tail;
+        Some(word)
+    })
+}
+
+// cut styled text down to `width` cells, ending in an ellipsis when cut
+fn truncate(spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    if spans.iter().map(Span::width).sum::<usize>() <= width {
+        return spans;
+    }
+    let mut cut = Vec::new();
+    let mut used = 0;
+    'spans: for span in spans {
+        let mut text = String::new();
+        for grapheme in span.content.graphemes(true) {
+            if used + grapheme.width() >= width {
+                cut.push(Span::styled(text, span.style));
+                break 'spans;
+            }
+            text.push_str(grapheme);
+            used += grapheme.width();
+        }
+        cut.push(Span::styled(text, span.style));
+    }
+    cut.push(Span::styled("…", highlight::marker()));
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(preview: &Preview) -> Vec<String> {
+        preview
+            .lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_renders_blocks() {
+        let note = [
+            "# Title",
+            "",
+            "Some *text* with a [link](http://x.y).",
+            "",
+            "- one",
+            "- [x] two",
+            "  1. nested",
+            "",
+            "> quoted",
+            "",
+            "```rust",
+            "let x = 1;",
+            "",
+            "```",
+            "---",
+            "| a | long column |",
+            "|--:|-------------|",
+            "| 1 | x |",
+        ];
+        let preview = Preview::render(&note.join("\n"), 20);
+        assert_eq!(
+            text(&preview),
+            vec![
+                "Title",
+                "",
+                "Some text with a",
+                "link (http://x.y).",
+                "",
+                "• one",
+                "• [x] two",
+                "  1. nested",
+                "",
+                "│ quoted",
+                "",
+                "  let x = 1;",
+                "  ",
+                "",
+                "────────────────────",
+                "",
+                "a │ long column",
+                "──┼────────────",
+                "1 │ x          ",
+            ]
+        );
+        assert_eq!(
+            preview.rows,
+            vec![0, 0, 2, 2, 2, 4, 5, 6, 6, 8, 8, 11, 12, 12, 14, 14, 15, 15, 17]
+        );
+        // code is colored by its language's grammar
+        let code = &preview.lines[11].spans[1..];
+        assert!(code.len() > 1 && code.iter().all(|span| span.style != highlight::code()));
+    }
+
+    #[test]
+    fn test_update_keeps_unchanged_code() {
+        let code = "```sh\necho hi\n```";
+        let mut preview = Preview::render(&format!("a\n\n{code}"), 20);
+        let key = ("sh".to_string(), "echo hi\n".to_string());
+        let styles = preview.highlighted[&key].clone();
+
+        preview.update(&format!("b\n\n{code}\n\n{code}"), 20);
+        assert_eq!(preview.highlighted.len(), 1);
+        assert_eq!(preview.highlighted[&key], styles);
+        assert_eq!(text(&preview)[2..], ["  echo hi", "", "  echo hi"]);
+
+        preview.update("```sh\nexit\n```", 20);
+        assert!(!preview.highlighted.contains_key(&key));
+    }
+
+    #[test]
+    fn test_lines_follow_note_rows() {
+        let note = ["para", "graph", "", "next", "", "last"].join("\n");
+        let preview = Preview::render(&note, 40);
+        assert_eq!(text(&preview), vec!["para graph", "", "next", "", "last"]);
+        // a row inside a block goes to the start of the block
+        assert_eq!(preview.line_at(1), 0);
+        assert_eq!(preview.line_at(2), 0);
+        assert_eq!(preview.line_at(3), 2);
+        assert_eq!(preview.line_at(9), 4);
+        assert_eq!(preview.row_at(2), 3);
+        assert_eq!(preview.row_at(9), 5);
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    graphemes,
+    note::{NoteId, NoteList},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+}
+
+impl SearchOptions {
+    // build the regex used for matching, plain queries are escaped
+    pub fn compile(&self, query: &str) -> Result<Regex> {
+        let pattern = if self.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        // half boundaries only look outside the match, so queries starting
+        // or ending with punctuation like `c++` still match as whole words
+        let pattern = if self.whole_word {
+            format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern)
+        } else {
+            pattern
+        };
+
+        Ok(RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()?)
+    }
+}
+
+// where a query matched, columns are grapheme indices like the editor cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub note: NoteId,
+    // None for a match in the title
+    pub line: Option<usize>,
+    pub start: usize,
+    pub end: usize,
+}
+
+// grapheme columns of the non-empty matches in a line
+pub fn find_in(regex: &Regex, text: &str) -> Vec<(usize, usize)> {
+    regex
+        .find_iter(text)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            (
+                graphemes::col_index(text, m.start()),
+                graphemes::col_index(text, m.end()),
+            )
+        })
+        .collect()
+}
+
+// every match of the query in note titles and content, in note order
+pub fn search(notes: &NoteList, query: &str, options: SearchOptions) -> Result<Vec<SearchMatch>> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let regex = options.compile(query)?;
+
+    let mut matches = Vec::new();
+    for note in notes.iter() {
+        for (start, end) in find_in(&regex, &note.title) {
+            matches.push(SearchMatch {
+                note: note.id,
+                line: None,
+                start,
+                end,
+            });
+        }
+        for (row, line) in note.content.iter().enumerate() {
+            for (start, end) in find_in(&regex, line) {
+                matches.push(SearchMatch {
+                    note: note.id,
+                    line: Some(row),
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+
+    fn notes() -> (NoteList, Note) {
+        let note = Note::new(
+            "Meeting notes",
+            vec![
+                "Discuss the budget".to_string(),
+                "budgeting is hard, ünïcode budget".to_string(),
+            ],
+        );
+        let mut note_list = NoteList::new();
+        note_list.insert(&note);
+        (note_list, note)
+    }
+
+    #[test]
+    fn test_case_insensitive_by_default() {
+        let (note_list, note) = notes();
+        let matches = search(&note_list, "MEETING", SearchOptions::default()).unwrap();
+        assert_eq!(
+            matches,
+            vec![SearchMatch {
+                note: note.id,
+                line: None,
+                start: 0,
+                end: 7,
+            }]
+        );
+
+        let options = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        assert!(search(&note_list, "MEETING", options).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_whole_word_and_char_columns() {
+        let (note_list, _) = notes();
+        let matches = search(&note_list, "budget", SearchOptions::default()).unwrap();
+        assert_eq!(matches.len(), 3);
+
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let matches = search(&note_list, "budget", options).unwrap();
+        let positions: Vec<(Option<usize>, usize)> =
+            matches.iter().map(|m| (m.line, m.start)).collect();
+        assert_eq!(positions, vec![(Some(0), 12), (Some(1), 27)]);
+    }
+
+    #[test]
+    fn test_whole_word_with_punctuation() {
+        let note = Note::new(
+            "",
+            vec!["use -x with c++ (a) but not a-x, c++x or b(a)".to_string()],
+        );
+        let mut note_list = NoteList::new();
+        note_list.insert(&note);
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        let starts = |query| {
+            search(&note_list, query, options)
+                .unwrap()
+                .iter()
+                .map(|m| m.start)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(starts("-x"), vec![4]);
+        assert_eq!(starts("c++"), vec![12]);
+        assert_eq!(starts("(a)"), vec![16]);
+    }
+
+    #[test]
+    fn test_regex_mode() {
+        let (note_list, _) = notes();
+        let options = SearchOptions {
+            regex: true,
+            ..SearchOptions::default()
+        };
+        let matches = search(&note_list, r"budget\w+", options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].start, matches[0].end), (0, 9));
+
+        assert!(search(&note_list, "(", options).is_err());
+        assert!(search(&note_list, "(", SearchOptions::default()).is_ok());
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+// path of the nth backup of a file, `notes.json.bak.1` being the newest
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", index));
+    PathBuf::from(name)
+}
+
+// existing backups of a file, newest first
+pub fn existing_backups(path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map(|index| backup_path(path, index))
+        .take_while(|backup| backup.exists())
+        .collect()
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_owned())
+        .unwrap_or_else(|| OsString::from("noted"));
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", process::id()));
+    path.with_file_name(temp_name)
+}
+
+// backups numbered above `keep`, left over from a higher `backups` setting
+fn stale_backups(path: &Path, keep: usize) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let dir = match dir.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => dir,
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.bak.", name.to_string_lossy());
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|index| index.parse::<usize>().ok())
+                .is_some_and(|index| index > keep)
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+// shift `file.bak.1..n-1` up by one and copy the current file into `file.bak.1`,
+// removing backups beyond `backups`. Without a backup count existing backups
+// are left alone
+fn rotate_backups(path: &Path, backups: Option<usize>) -> Result<()> {
+    let Some(backups) = backups else {
+        return Ok(());
+    };
+    for stale in stale_backups(path, backups) {
+        fs::remove_file(stale)?;
+    }
+    if backups == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let oldest = backup_path(path, backups);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for index in (1..backups).rev() {
+        let from = backup_path(path, index);
+        if from.exists() {
+            fs::rename(&from, backup_path(path, index + 1))?;
+        }
+    }
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all()?,
+        _ => File::open(".")?.sync_all()?,
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn write_temp<F>(temp: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let file = File::create(temp)?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+// write to a temp file next to `path`, fsync it and rename it over the
+// original so a failed write never leaves a truncated file behind
+pub fn atomic_write<F>(path: &Path, backups: Option<usize>, write: F) -> Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> Result<()>,
+{
+    let temp = temp_path(path);
+
+    if let Err(e) = write_temp(&temp, write) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.context(format!("Couldn't write {}", path.display())));
+    }
+
+    let result = rotate_backups(path, backups)
+        .with_context(|| format!("Couldn't rotate backups of {}", path.display()))
+        .and_then(|_| {
+            fs::rename(&temp, path).with_context(|| format!("Couldn't replace {}", path.display()))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
+    }
+
+    sync_dir(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_str(contents: &str) -> impl FnOnce(&mut dyn Write) -> Result<()> + '_ {
+        move |w| {
+            w.write_all(contents.as_bytes())?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(&path, "old").unwrap();
+
+        atomic_write(&path, Some(0), write_str("new")).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_failing_writer_leaves_original_untouched() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+        fs::write(&path, "original").unwrap();
+
+        let result = atomic_write(&path, Some(2), |w| {
+            w.write_all(b"partial")?;
+            anyhow::bail!("disk full")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+        assert!(!backup_path(&path, 1).exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_backups_are_rotated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+
+        for contents in ["v1", "v2", "v3", "v4"] {
+            atomic_write(&path, Some(2), write_str(contents)).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v4");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "v2");
+        assert!(!backup_path(&path, 3).exists());
+        assert_eq!(
+            existing_backups(&path),
+            vec![backup_path(&path, 1), backup_path(&path, 2)]
+        );
+    }
+
+    #[test]
+    fn test_lowering_the_limit_removes_extra_backups() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+
+        for contents in ["v1", "v2", "v3", "v4"] {
+            atomic_write(&path, Some(3), write_str(contents)).unwrap();
+        }
+        assert!(backup_path(&path, 3).exists());
+
+        atomic_write(&path, Some(1), write_str("v5")).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v4");
+        assert!(!backup_path(&path, 2).exists());
+        assert!(!backup_path(&path, 3).exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_backups_are_kept_without_a_count() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+
+        atomic_write(&path, Some(2), write_str("v1")).unwrap();
+        atomic_write(&path, Some(2), write_str("v2")).unwrap();
+        atomic_write(&path, None, write_str("v3")).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v3");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_no_backups_when_disabled() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.json");
+
+        atomic_write(&path, Some(0), write_str("v1")).unwrap();
+        atomic_write(&path, Some(0), write_str("v2")).unwrap();
+
+        assert!(!backup_path(&path, 1).exists());
+    }
+}
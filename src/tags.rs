@@ -0,0 +1,232 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::note::Note;
+
+fn is_tag_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '-' | '_' | '/')
+}
+
+// normalise user input like "#Work, urgent" into ["work", "urgent"]. Words
+// that aren't valid tags, like "c++", come back separately so they can be
+// reported
+pub fn parse_tags(input: &str) -> (Vec<String>, Vec<String>) {
+    let mut tags: Vec<String> = Vec::new();
+    let mut invalid: Vec<String> = Vec::new();
+    for word in input.split(|ch: char| ch == ',' || ch.is_whitespace()) {
+        let tag = word.trim_start_matches('#').to_lowercase();
+        if tag.is_empty() || tags.contains(&tag) {
+            continue;
+        }
+        match tag.chars().all(is_tag_char) {
+            true => tags.push(tag),
+            false => invalid.push(word.to_string()),
+        }
+    }
+    (tags, invalid)
+}
+
+// length of the run of backticks `text` starts with
+fn backticks(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
+
+// the char and length of a fence opening or closing a code block, like
+// "```rust" or "~~~", and the text after it
+fn fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = trimmed
+        .chars()
+        .next()
+        .filter(|&ch| ch == '`' || ch == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(ch).len();
+    let info = trimmed[len..].trim();
+    // a backtick fence can't have backticks after it, that's inline code
+    (len >= 3 && !(ch == '`' && info.contains('`'))).then_some((ch, len, info))
+}
+
+// the line with its inline code spans blanked out. A span opened by a run of
+// backticks is closed by a run of as many, without one they're plain text
+fn without_code_spans(line: &str) -> String {
+    let mut text = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('`') {
+        text.push_str(&rest[..start]);
+        let run = backticks(&rest[start..]);
+        let after = &rest[start + run..];
+        let mut from = 0;
+        let close = loop {
+            let Some(i) = after[from..].find('`') else {
+                break None;
+            };
+            let len = backticks(&after[from + i..]);
+            if len == run {
+                break Some(from + i + len);
+            }
+            from += i + len;
+        };
+        match close {
+            Some(end) => {
+                text.push(' ');
+                rest = &after[end..];
+            }
+            None => {
+                text.push_str(&rest[start..start + run]);
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// `#tag` words in the note content, a `#` must start a word and be followed
+// by a tag character so markdown headings aren't picked up. Code blocks and
+// code spans are left out, so `#include` or `#fff` aren't tags
+pub fn extract_tags(content: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    // the fence of the code block the line is in
+    let mut block: Option<(char, usize)> = None;
+    for line in content {
+        match (block, fence(line)) {
+            (Some((ch, len)), Some((closing, closing_len, "")))
+                if closing == ch && closing_len >= len =>
+            {
+                block = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, Some((ch, len, _))) => {
+                block = Some((ch, len));
+                continue;
+            }
+            (None, None) => {}
+        }
+        for word in without_code_spans(line).split_whitespace() {
+            let Some(rest) = word.strip_prefix('#') else {
+                continue;
+            };
+            let tag: String = rest.chars().take_while(|&ch| is_tag_char(ch)).collect();
+            let tag = tag.trim_end_matches(['-', '/']).to_lowercase();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagMatch {
+    All,
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub mode: TagMatch,
+}
+
+impl TagFilter {
+    pub fn new(tags: Vec<String>, mode: TagMatch) -> Self {
+        TagFilter { tags, mode }
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            TagMatch::All => TagMatch::Any,
+            TagMatch::Any => TagMatch::All,
+        }
+    }
+
+    pub fn matches(&self, note: &Note) -> bool {
+        let has = |tag: &String| note.has_tag(tag);
+        match self.mode {
+            TagMatch::All => self.tags.iter().all(has),
+            TagMatch::Any => self.tags.iter().any(has),
+        }
+    }
+}
+
+impl Display for TagFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sep = match self.mode {
+            TagMatch::All => " AND ",
+            TagMatch::Any => " OR ",
+        };
+        let tags: Vec<String> = self.tags.iter().map(|tag| format!("#{}", tag)).collect();
+        write!(f, "{}", tags.join(sep))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tags() {
+        let (tags, invalid) = parse_tags("#Work, urgent  work");
+        assert_eq!(tags, vec!["work", "urgent"]);
+        assert!(invalid.is_empty());
+        assert_eq!(parse_tags(" , # "), (Vec::new(), Vec::new()));
+        assert_eq!(
+            parse_tags("rust #c++ c#"),
+            (
+                vec!["rust".to_string()],
+                vec!["#c++".to_string(), "c#".into()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_extract_inline_tags() {
+        let content = vec![
+            "# Heading".to_string(),
+            "call #acme about #billing, then".to_string(),
+            "issue#3 and #work/clients".to_string(),
+        ];
+        assert_eq!(
+            extract_tags(&content),
+            vec!["acme", "billing", "work/clients"]
+        );
+    }
+
+    #[test]
+    fn test_code_isnt_tagged() {
+        let content = vec![
+            "```c".to_string(),
+            "#include <stdio.h>".to_string(),
+            "```".to_string(),
+            "set `color: #fff` or ``#[derive]`` on #style".to_string(),
+            "a lone ` before #tick".to_string(),
+            "~~~~".to_string(),
+            "#notatag".to_string(),
+            "~~~".to_string(),
+            "```".to_string(),
+            "#notatag".to_string(),
+            "~~~~".to_string(),
+        ];
+        assert_eq!(extract_tags(&content), vec!["style", "tick"]);
+    }
+
+    #[test]
+    fn test_filter_modes() {
+        let mut note = Note::new("", vec!["#inline".into()]);
+        note.set_tags(vec!["work".into()]);
+
+        let mut filter = TagFilter::new(vec!["work".into(), "inline".into()], TagMatch::All);
+        assert!(filter.matches(&note));
+
+        filter.tags.push("missing".into());
+        assert!(!filter.matches(&note));
+
+        filter.toggle_mode();
+        assert!(filter.matches(&note));
+
+        let filter = TagFilter::new(vec!["missing".into()], TagMatch::Any);
+        assert!(!filter.matches(&note));
+    }
+}
@@ -0,0 +1,259 @@
+use std::ops::Range;
+
+use chrono::NaiveDate;
+
+use crate::{
+    highlight::{Highlighter, LineState, Markdown},
+    note::{NoteId, NoteList},
+};
+
+// Tasks are list items starting with a checkbox, `- [ ] call the bank`, in
+// any note outside code blocks. A task can carry a due date as a word like
+// `due:2024-05-01`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub note: NoteId,
+    pub line: usize,
+    // the text after the checkbox
+    pub text: String,
+    pub due: Option<NaiveDate>,
+}
+
+// byte offsets into a list item line
+struct Item {
+    // where the bullet starts, after indentation and quote markers
+    start: usize,
+    // where the text starts, after the bullet and checkbox
+    text: usize,
+    // the bullet without the space after it, "-" or "1."
+    bullet: Range<usize>,
+    // None for an item without a checkbox
+    checked: Option<bool>,
+}
+
+// length of a list bullet and the space after it, "- " or "1. "
+pub fn bullet_len(line: &str) -> Option<usize> {
+    let digits = line.len()
+        - line
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let marker = match digits {
+        0 => line
+            .chars()
+            .next()
+            .filter(|ch| matches!(ch, '-' | '*' | '+'))?,
+        1..=9 => line[digits..]
+            .chars()
+            .next()
+            .filter(|ch| matches!(ch, '.' | ')'))?,
+        _ => return None,
+    };
+    let len = digits + marker.len_utf8();
+    match line[len..].chars().next() {
+        Some(' ') => Some(len + 1),
+        None => Some(len),
+        _ => None,
+    }
+}
+
+// whether a line after its bullet starts with a checked or unchecked box
+pub fn checkbox(line: &str) -> Option<bool> {
+    let checked = match line.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    line[3..]
+        .chars()
+        .next()
+        .is_none_or(|ch| ch == ' ')
+        .then_some(checked)
+}
+
+// bytes of indentation and quote markers before a line's text
+fn line_start(line: &str) -> usize {
+    let mut at = line.len() - line.trim_start_matches(' ').len();
+    while let Some(quoted) = line[at..].strip_prefix('>') {
+        at = line.len() - quoted.trim_start_matches(' ').len();
+    }
+    at
+}
+
+fn item(line: &str) -> Option<Item> {
+    let start = line_start(line);
+    let len = bullet_len(&line[start..])?;
+    let bullet = start..start + line[start..start + len].trim_end().len();
+    let checked = checkbox(&line[start + len..]);
+    let text = match checked {
+        Some(_) => (start + len + 4).min(line.len()),
+        None => start + len,
+    };
+    Some(Item {
+        start,
+        text,
+        bullet,
+        checked,
+    })
+}
+
+// the edit that toggles a line's checkbox, as the byte range to replace and
+// its replacement. A list item without a checkbox gets one, any other line
+// becomes a task
+pub fn toggle(line: &str) -> (Range<usize>, &'static str) {
+    let Some(item) = item(line) else {
+        let start = line_start(line);
+        return (start..start, "- [ ] ");
+    };
+    let after = item.bullet.end;
+    match item.checked {
+        Some(true) => (after + 2..after + 3, " "),
+        Some(false) => (after + 2..after + 3, "x"),
+        None if item.text > after => (item.text..item.text, "[ ] "),
+        None => (after..after, " [ ] "),
+    }
+}
+
+// the line with its open task checked, None if it has no open task
+pub fn complete(line: &str) -> Option<String> {
+    item(line).filter(|item| item.checked == Some(false))?;
+    let (range, replacement) = toggle(line);
+    let mut line = line.to_string();
+    line.replace_range(range, replacement);
+    Some(line)
+}
+
+// the start of the line Enter opens at `byte` of a list item: the same
+// indentation and bullet, one more for a numbered bullet, and an unchecked
+// box after a task. None outside list items or within the bullet
+pub fn continuation(line: &str, byte: usize) -> Option<String> {
+    let item = item(line).filter(|item| byte >= item.text)?;
+    let bullet = &line[item.bullet.clone()];
+    let next = match bullet.split_at(bullet.len() - 1) {
+        ("", marker) => marker.to_string(),
+        (number, delimiter) => format!("{}{}", number.parse::<u64>().ok()? + 1, delimiter),
+    };
+    let checkbox = match item.checked {
+        Some(_) => "[ ] ",
+        None => "",
+    };
+    Some(format!("{}{} {}", &line[..item.start], next, checkbox))
+}
+
+// the bullet and checkbox of an item with no text, which Enter removes to end
+// the list
+pub fn empty_item(line: &str) -> Option<Range<usize>> {
+    let item = item(line).filter(|item| line[item.text..].trim().is_empty())?;
+    Some(item.start..line.len())
+}
+
+// the date of the first `due:YYYY-MM-DD` word
+pub fn due_date(text: &str) -> Option<NaiveDate> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix("due:"))
+        .find_map(|date| {
+            let date = date.trim_end_matches(|ch: char| !ch.is_ascii_digit());
+            NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+        })
+}
+
+// the unchecked tasks of every note, in note order so a note's tasks are
+// together
+pub fn open_tasks(notes: &NoteList) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    for note in notes.iter() {
+        let mut state = LineState::default();
+        for (row, line) in note.content.iter().enumerate() {
+            let in_code = state != LineState::Text;
+            Markdown.skip(line, &mut state);
+            if in_code {
+                continue;
+            }
+            let Some(item) = item(line).filter(|item| item.checked == Some(false)) else {
+                continue;
+            };
+            let text = line[item.text..].trim().to_string();
+            tasks.push(Task {
+                note: note.id,
+                line: row,
+                due: due_date(&text),
+                text,
+            });
+        }
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+
+    fn toggled(line: &str) -> String {
+        let (range, replacement) = toggle(line);
+        let mut line = line.to_string();
+        line.replace_range(range, replacement);
+        line
+    }
+
+    #[test]
+    fn test_toggle() {
+        assert_eq!(toggled("- [ ] call"), "- [x] call");
+        assert_eq!(toggled("  * [X] call"), "  * [ ] call");
+        assert_eq!(toggled("1. call"), "1. [ ] call");
+        assert_eq!(toggled("> call"), "> - [ ] call");
+        assert_eq!(toggled("-"), "- [ ] ");
+        assert_eq!(toggled(""), "- [ ] ");
+        assert_eq!(complete("- [ ] call"), Some("- [x] call".to_string()));
+        assert_eq!(complete("- [x] call"), None);
+        assert_eq!(complete("[ ] call"), None);
+    }
+
+    #[test]
+    fn test_continuation() {
+        let next = |line: &str| continuation(line, line.len());
+        assert_eq!(next("- one"), Some("- ".to_string()));
+        assert_eq!(next("  > * [x] done"), Some("  > * [ ] ".to_string()));
+        assert_eq!(next("9) nine"), Some("10) ".to_string()));
+        assert_eq!(next("-not a bullet"), None);
+        assert_eq!(continuation("- one", 1), None);
+
+        assert_eq!(empty_item("  - [ ] "), Some(2..8));
+        assert_eq!(empty_item("1."), Some(0..2));
+        assert_eq!(empty_item("- one"), None);
+    }
+
+    #[test]
+    fn test_open_tasks() {
+        let note = Note::new(
+            "Chores",
+            vec![
+                "- [ ] water plants due:2024-05-01".to_string(),
+                "- [x] dishes".to_string(),
+                "```".to_string(),
+                "- [ ] in a code block".to_string(),
+                "```".to_string(),
+                "  1. [ ] bins (due:tomorrow)".to_string(),
+            ],
+        );
+        let mut notes = NoteList::new();
+        notes.insert(&note);
+
+        let tasks = open_tasks(&notes);
+        let found: Vec<(usize, &str, Option<NaiveDate>)> = tasks
+            .iter()
+            .map(|task| (task.line, task.text.as_str(), task.due))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    0,
+                    "water plants due:2024-05-01",
+                    NaiveDate::from_ymd_opt(2024, 5, 1)
+                ),
+                (5, "bins (due:tomorrow)", None),
+            ]
+        );
+    }
+}
@@ -3,26 +3,207 @@ use ratatui::{
     layout::Rect,
     style::{Color, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Widget, Wrap},
+    widgets::{Block, Borders, Paragraph, Widget},
 };
 
-use std::cmp::min;
+use regex::Regex;
+use std::{cmp::min, collections::VecDeque, ops::Range, rc::Rc};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    config::{EditingMode, VerticalMovement, WrapMode},
+    graphemes,
+    highlight::{Highlighter, LineState},
+    tasks,
+};
+use buffer::Buffer;
+
+mod buffer;
+mod emacs;
+mod find;
+mod layout;
+mod vim;
 
 const HEIGHT_PADDING: usize = 3;
+// row and grapheme column in the text
+type Pos = (usize, usize);
+
+// how many undo steps are kept, older ones are dropped
+const UNDO_LIMIT: usize = 200;
+
+// how many lines above the screen are highlighted in full to find the state
+// at its top, lines further up are only skipped through
+const SYNC_LINES: usize = 200;
 
 #[derive(Clone, Debug)]
 pub struct Cursor {
     pub row: usize,
     pub col: usize,
+    // screen column kept when moving up and down through shorter rows
     pub latch_col: usize,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+}
+
+// a single change to the text, `text` may span lines joined with '\n'. It is
+// placed by byte offset since an edit can merge or split grapheme clusters,
+// e.g. typing a combining accent after a letter
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Edit {
+    kind: EditKind,
+    row: usize,
+    byte: usize,
+    text: String,
+}
+
+impl Edit {
+    fn is_whitespace(&self) -> bool {
+        self.text.chars().all(char::is_whitespace)
+    }
+
+    // true if `next` continues typing or backspacing right where this edit ended
+    fn continues_with(&self, next: &Edit) -> bool {
+        if self.kind != next.kind || self.row != next.row || self.text.contains('\n') {
+            return false;
+        }
+        match next.kind {
+            // typing a space after a word starts a new undo step
+            EditKind::Insert => {
+                next.byte == self.byte + self.text.len()
+                    && (self.is_whitespace() || !next.is_whitespace())
+            }
+            EditKind::Delete => {
+                next.byte + next.text.len() == self.byte && !next.text.contains('\n')
+            }
+        }
+    }
+}
+
+// edits undone and redone together, with the cursor before and after them
+#[derive(Clone, Debug)]
+struct UndoStep {
+    edits: Vec<Edit>,
+    before: (usize, usize),
+    after: (usize, usize),
+}
+
+#[derive(Clone, Debug, Default)]
+struct History {
+    undo: VecDeque<UndoStep>,
+    redo: Vec<UndoStep>,
+    // whether the next edit may be merged into the last undo step
+    coalesce: bool,
+    // merge the next edit regardless, e.g. typing over a selection
+    join: bool,
+    // while set, edits keep joining the current undo step, e.g. a Vim change
+    grouping: bool,
+}
+
+impl History {
+    fn record(&mut self, edit: Edit, before: (usize, usize), after: (usize, usize)) {
+        self.redo.clear();
+        let join = std::mem::take(&mut self.join) || (self.coalesce && self.grouping);
+        if let Some(step) = self.undo.back_mut() {
+            let continues = self.coalesce
+                && step
+                    .edits
+                    .last()
+                    .is_some_and(|last| last.continues_with(&edit));
+            if join || continues {
+                step.edits.push(edit);
+                step.after = after;
+                return;
+            }
+        }
+
+        self.undo.push_back(UndoStep {
+            edits: vec![edit],
+            before,
+            after,
+        });
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        self.coalesce = true;
+    }
+
+    // stop merging, the next edit starts a new undo step
+    fn seal(&mut self) {
+        self.coalesce = false;
+        self.join = false;
+    }
+
+    fn begin_group(&mut self) {
+        self.seal();
+        self.grouping = true;
+    }
+
+    fn end_group(&mut self) {
+        self.grouping = false;
+        self.seal();
+    }
+
+    fn clear(&mut self) {
+        *self = History::default();
+    }
+}
+
+// key handling layered over the default editor keys, picked by `editing_mode`
+#[derive(Clone, Debug, Default)]
+pub enum Keymap {
+    #[default]
+    Default,
+    Vim(vim::Vim),
+    Emacs(emacs::Emacs),
+}
+
+// editing commands the app binds to keys, they work the same in every keymap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditCommand {
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    ToggleTask,
+}
+
 #[derive(Clone, Debug)]
 pub struct TextBox {
-    pub text: Vec<String>,
+    pub text: Buffer,
     pub cursor: Cursor,
-    pub visible_lines: (usize, usize),
+    // first screen row shown, as a row and which of its wrapped rows
+    scroll: (usize, usize),
+    // first screen column shown when lines aren't wrapped
+    hscroll: usize,
+    // width and height of the text area from the last render
+    viewport: Option<(usize, usize)>,
     pub terminal_height: usize,
+    // the other end of the selection, the cursor being the end that moves
+    pub anchor: Option<(usize, usize)>,
+    // text of the last cut or copy
+    pub register: String,
+    // whether the register holds whole lines, from Vim's `dd` or `yy`
+    linewise: bool,
+    // counts changes to the register, so copies reach the system clipboard
+    register_revision: usize,
+    pub keymap: Keymap,
+    history: History,
+    wrap_mode: WrapMode,
+    vertical_movement: VerticalMovement,
+    // matches of the find bar's query, highlighted when shown
+    highlight: Option<Regex>,
+    highlighter: Option<Rc<dyn Highlighter>>,
+    // highlighter states at the start of the lines down to the top of the
+    // screen, so drawing doesn't highlight the note from its first line
+    line_states: Vec<LineState>,
+    // counts changes to the text, so views of it know when to redraw
+    revision: usize,
 }
 
 impl From<Vec<String>> for TextBox {
@@ -31,14 +212,28 @@ impl From<Vec<String>> for TextBox {
             .map(|(_, height)| height as usize)
             .unwrap_or_default(); // - HEIGHT_PADDING;
         Self {
-            text: v,
+            text: Buffer::from(v),
             cursor: Cursor {
                 row: 0,
                 col: 0,
                 latch_col: 0,
             },
-            visible_lines: (0, term_height),
+            scroll: (0, 0),
+            hscroll: 0,
+            viewport: None,
             terminal_height: term_height,
+            anchor: None,
+            register: String::new(),
+            linewise: false,
+            register_revision: 0,
+            keymap: Keymap::Default,
+            history: History::default(),
+            wrap_mode: WrapMode::default(),
+            vertical_movement: VerticalMovement::default(),
+            highlight: None,
+            highlighter: None,
+            line_states: Vec::new(),
+            revision: 0,
         }
     }
 }
@@ -46,271 +241,928 @@ impl From<Vec<String>> for TextBox {
 impl TextBox {
     pub fn new(terminal_height: usize) -> Self {
         TextBox {
-            text: Vec::new(),
+            text: Buffer::default(),
             cursor: Cursor {
                 row: 0,
                 col: 0,
                 latch_col: 0,
             },
-            visible_lines: (0, terminal_height),// -HEIGHT_PADDING),
-            terminal_height: terminal_height,// -HEIGHT_PADDING,
+            scroll: (0, 0),
+            hscroll: 0,
+            viewport: None,
+            terminal_height, // -HEIGHT_PADDING,
+            anchor: None,
+            register: String::new(),
+            linewise: false,
+            register_revision: 0,
+            keymap: Keymap::Default,
+            history: History::default(),
+            wrap_mode: WrapMode::default(),
+            vertical_movement: VerticalMovement::default(),
+            highlight: None,
+            highlighter: None,
+            line_states: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    pub fn set_wrap_mode(&mut self, mode: WrapMode) {
+        self.wrap_mode = mode;
+        self.hscroll = 0;
+    }
+
+    pub fn set_vertical_movement(&mut self, movement: VerticalMovement) {
+        self.vertical_movement = movement;
+    }
+
+    // the text area inside the borders, set by the ui before each render
+    pub fn set_viewport(&mut self, width: usize, height: usize) {
+        self.viewport = Some((width, height));
+        self.update_visible_lines();
+    }
+
+    pub fn set_highlighter(&mut self, highlighter: Option<Rc<dyn Highlighter>>) {
+        self.highlighter = highlighter;
+        self.line_states.clear();
+        self.keep_line_states(self.scroll.0);
+    }
+
+    pub fn set_editing_mode(&mut self, mode: EditingMode) {
+        self.keymap = match mode {
+            EditingMode::Default => Keymap::Default,
+            EditingMode::Vim => Keymap::Vim(vim::Vim::default()),
+            EditingMode::Emacs => Keymap::Emacs(emacs::Emacs::default()),
+        };
+    }
+
+    pub fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        let mut keymap = std::mem::take(&mut self.keymap);
+        match &mut keymap {
+            Keymap::Default => self.handle_default(key, modifiers),
+            Keymap::Vim(vim) => vim.handle_input(self, key, modifiers),
+            Keymap::Emacs(emacs) => emacs.handle_input(self, key, modifiers),
+        }
+        self.keymap = keymap;
+    }
+
+    // text pasted into the terminal, inserted at the cursor whatever the
+    // keymap's mode
+    pub fn paste_text(&mut self, text: &str) {
+        let mut keymap = std::mem::take(&mut self.keymap);
+        match &mut keymap {
+            Keymap::Default => self.insert_str(text),
+            Keymap::Vim(vim) => vim.paste_text(self, text),
+            Keymap::Emacs(emacs) => {
+                self.insert_str(text);
+                emacs.after_command()
+            }
+        }
+        self.keymap = keymap;
+        self.update_visible_lines();
+    }
+
+    // run an editing command bound in the keybindings file
+    pub fn command(&mut self, command: EditCommand) {
+        self.history.seal();
+        match command {
+            EditCommand::Undo => self.undo(),
+            EditCommand::Redo => self.redo(),
+            EditCommand::Copy => self.copy(),
+            EditCommand::Cut => self.cut(),
+            EditCommand::Paste => self.paste(),
+            EditCommand::ToggleTask => self.toggle_task(),
+        }
+        let mut keymap = std::mem::take(&mut self.keymap);
+        match &mut keymap {
+            Keymap::Default => {}
+            Keymap::Vim(vim) => vim.after_command(self),
+            Keymap::Emacs(emacs) => emacs.after_command(),
+        }
+        self.keymap = keymap;
+        self.update_visible_lines();
+    }
+
+    // whether the keymap binds a key the app would otherwise handle, e.g. C-p
+    pub fn claims_key(&self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        match &self.keymap {
+            Keymap::Emacs(_) => emacs::is_bound(key, modifiers),
+            Keymap::Vim(vim) => vim.claims_key(key, modifiers),
+            Keymap::Default => false,
+        }
+    }
+
+    // whether Esc is used by the keymap rather than closing the editor
+    pub fn wants_esc(&self) -> bool {
+        match &self.keymap {
+            Keymap::Default | Keymap::Emacs(_) => false,
+            Keymap::Vim(vim) => vim.wants_esc(),
+        }
+    }
+
+    // keys of the keymap layer shown after the editor's key hints
+    pub fn keymap_hints(&self) -> Option<String> {
+        match &self.keymap {
+            Keymap::Default | Keymap::Vim(_) => None,
+            Keymap::Emacs(_) => Some(emacs::hints()),
+        }
+    }
+
+    // shown in the nav bar, e.g. the Vim mode
+    pub fn mode_indicator(&self) -> Option<String> {
+        match &self.keymap {
+            Keymap::Default | Keymap::Emacs(_) => None,
+            Keymap::Vim(vim) => Some(vim.indicator()),
         }
     }
 
-    pub fn handle_input(&mut self, key: KeyCode, _modifiers: KeyModifiers) {
+    fn handle_default(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let editing =
+            !ctrl && matches!(key, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter);
+        if !editing {
+            self.history.seal();
+        }
+
+        // shift extends the selection, plain movement drops it. Terminals send
+        // Shift+Tab as BackTab, which selects back a word
+        let moving = matches!(
+            key,
+            KeyCode::Right | KeyCode::Left | KeyCode::Down | KeyCode::Up | KeyCode::Tab
+        );
+        if (moving && shift) || key == KeyCode::BackTab {
+            self.anchor
+                .get_or_insert((self.cursor.row, self.cursor.col));
+        } else if moving {
+            self.anchor = None;
+        }
+
         match key {
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Right if ctrl => self.move_cursor_next_word(),
+            KeyCode::Left if ctrl => self.move_cursor_prev_word(),
             KeyCode::Right => self.move_cursor_right(),
             KeyCode::Left => self.move_cursor_left(),
             KeyCode::Down => self.move_cursor_down(),
             KeyCode::Up => self.move_cursor_up(),
-            KeyCode::Enter => self.insert_newline(),
-            KeyCode::Char(ch) => self.insert_char(ch),
+            KeyCode::Enter => {
+                self.delete_selection();
+                self.insert_newline()
+            }
+            KeyCode::Char(ch) => {
+                self.delete_selection();
+                self.insert_char(ch)
+            }
+            KeyCode::Backspace if self.selection().is_some() => {
+                self.delete_selection();
+            }
             KeyCode::Backspace => self.delete_char(),
             KeyCode::Tab => self.move_cursor_next_word(),
             KeyCode::BackTab => self.move_cursor_prev_word(),
             _ => {}
         }
+        self.history.join = false;
+        self.update_visible_lines();
+    }
+
+    // the selected range in text order, None if nothing is selected
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.anchor?;
+        let cursor = (self.cursor.row, self.cursor.col);
+        match anchor.cmp(&cursor) {
+            std::cmp::Ordering::Less => Some((anchor, cursor)),
+            std::cmp::Ordering::Greater => Some((cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.text_between(start, end))
+    }
+
+    // the selected columns of a line, `usize::MAX` as the end when the
+    // selection continues onto the next line
+    fn selected_cols(&self, row: usize) -> Option<(usize, usize)> {
+        let ((start_row, start_col), (end_row, end_col)) = self.selection()?;
+        if row < start_row || row > end_row {
+            return None;
+        }
+        let start = if row == start_row { start_col } else { 0 };
+        let end = if row == end_row { end_col } else { usize::MAX };
+        Some((start, end))
+    }
+
+    // remove the selected text as one undo step with the edit that follows
+    fn delete_selection(&mut self) -> bool {
+        let Some(((row, col), end)) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        self.anchor = None;
+        self.history.seal();
+        self.delete_range((row, col), end);
+        self.history.join = true;
+        true
+    }
+
+    fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) {
+        let text = self.text_between(start, end);
+        if !text.is_empty() {
+            self.edit(EditKind::Delete, start.0, start.1, text);
+        }
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        graphemes::len(&self.text.line(row))
+    }
+
+    // the first char of the grapheme under a position, the end of a line
+    // reads as a line break
+    fn char_at(&self, (row, col): Pos) -> char {
+        graphemes::nth(&self.text.line(row), col)
+            .and_then(|grapheme| grapheme.chars().next())
+            .unwrap_or('\n')
+    }
+
+    // the column shown at the latched screen column of a row
+    fn latched_col(&self, row: usize) -> usize {
+        graphemes::col_at_width(&self.text.line(row), self.cursor.latch_col)
+    }
+
+    // the column at the latched screen column of one wrapped row, a row that
+    // continues on the next can't take the column the next one starts at
+    fn latched_col_in(&self, row: usize, rows: &[(usize, usize)], sub: usize) -> usize {
+        let line = &*self.text.line(row);
+        let (start, end) = rows[sub];
+        let shown = &line[graphemes::byte_index(line, start)..graphemes::byte_index(line, end)];
+        let col = start + graphemes::col_at_width(shown, self.cursor.latch_col);
+        match sub + 1 < rows.len() {
+            true => min(col, end.saturating_sub(1)),
+            false => col,
+        }
+    }
+
+    fn latch(&mut self) {
+        let line = &*self.text.line(self.cursor.row);
+        self.cursor.latch_col = match self.vertical_movement {
+            VerticalMovement::Visual => self.cursor_visual().1,
+            VerticalMovement::Logical => graphemes::width_to(line, self.cursor.col),
+        };
+    }
+
+    fn height(&self) -> usize {
+        let height = match self.viewport {
+            Some((_, height)) => height,
+            None => self.terminal_height.saturating_sub(HEIGHT_PADDING + 1),
+        };
+        height.max(1)
+    }
+
+    // the screen rows of a line as column ranges. A column is left free at the
+    // end of a wrapped row for the cursor to sit past the last char
+    fn line_rows(&self, row: usize) -> Vec<(usize, usize)> {
+        let line = &*self.text.line(row);
+        match (self.wrap_mode, self.viewport) {
+            (WrapMode::Soft, Some((width, _))) => {
+                layout::wrap_line(line, width.saturating_sub(1).max(1))
+            }
+            _ => vec![(0, graphemes::len(line))],
+        }
+    }
+
+    // the wrapped row of the line the cursor is on, and its screen column
+    fn cursor_visual(&self) -> (usize, usize) {
+        let line = &*self.text.line(self.cursor.row);
+        let rows = self.line_rows(self.cursor.row);
+        let sub = layout::row_of(&rows, self.cursor.col);
+        let x = graphemes::width_between(line, rows[sub].0, self.cursor.col);
+        (sub, x)
+    }
+
+    fn prev_visual(&self, (row, sub): (usize, usize)) -> (usize, usize) {
+        match (row, sub) {
+            (_, 1..) => (row, sub - 1),
+            (1.., 0) => (row - 1, self.line_rows(row - 1).len() - 1),
+            _ => (0, 0),
+        }
+    }
+
+    fn next_pos(&self, (row, col): Pos) -> Option<Pos> {
+        if col < self.line_len(row) {
+            Some((row, col + 1))
+        } else if row + 1 < self.text.len() {
+            Some((row + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    fn prev_pos(&self, (row, col): Pos) -> Option<Pos> {
+        if col > 0 {
+            Some((row, col - 1))
+        } else if row > 0 {
+            Some((row - 1, self.line_len(row - 1)))
+        } else {
+            None
+        }
+    }
+
+    pub fn copy(&mut self) {
+        if let Some(text) = self.selected_text() {
+            self.set_register(text, false);
+        }
+    }
+
+    // every keymap's cuts and copies go through here
+    pub fn set_register(&mut self, text: String, linewise: bool) {
+        self.register = text;
+        self.linewise = linewise;
+        self.register_revision += 1;
+    }
+
+    pub fn register_revision(&self) -> usize {
+        self.register_revision
+    }
+
+    pub fn cut(&mut self) {
+        self.copy();
+        self.delete_selection();
+        self.history.seal();
+    }
+
+    // insert the register at the cursor, replacing the selection
+    pub fn paste(&mut self) {
+        let text = self.register.clone();
+        self.insert_str(&text);
+    }
+
+    // insert text, possibly spanning lines, as a single undo step
+    pub fn insert_str(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if text.is_empty() {
+            return;
+        }
+        self.history.seal();
+        self.delete_selection();
+        self.edit(EditKind::Insert, self.cursor.row, self.cursor.col, text);
+        self.history.seal();
         self.update_visible_lines();
     }
 
     fn move_cursor_right(&mut self) {
         let (row, col) = (self.cursor.row, self.cursor.col);
-        let row_len = if self.text[row].is_empty() {
-            0
-        } else {
-            self.text[row].chars().count()
-        };
+        let row_len = self.line_len(row);
 
-        if col == row_len && row < self.text.len() - 1 {
+        if col == row_len && row + 1 < self.text.len() {
             self.cursor.col = 0;
             self.cursor.row = row + 1;
         } else if col < row_len {
             self.cursor.col = col + 1;
         }
 
-        self.cursor.latch_col = self.cursor.col
+        self.latch()
     }
 
     fn move_cursor_left(&mut self) {
         let (row, col) = (self.cursor.row, self.cursor.col);
 
         if col == 0 && row > 0 {
-            let prev_row_len = if self.text[row - 1].is_empty() {
-                0
-            } else {
-                self.text[row - 1].chars().count()
-            };
             self.cursor.row = row - 1;
-            self.cursor.col = prev_row_len;
+            self.cursor.col = self.line_len(row - 1);
         } else if col > 0 {
             self.cursor.col = col - 1;
         }
 
-        self.cursor.latch_col = self.cursor.col
+        self.latch()
     }
 
     fn move_cursor_down(&mut self) {
-        let (row, col) = (self.cursor.row, self.cursor.col);
-        if row < self.text.len() - 1 {
+        let row = self.cursor.row;
+        let visual = self.vertical_movement == VerticalMovement::Visual;
+        let rows = self.line_rows(row);
+        let sub = layout::row_of(&rows, self.cursor.col);
+
+        if visual && sub + 1 < rows.len() {
+            self.cursor.col = self.latched_col_in(row, &rows, sub + 1)
+        } else if row + 1 < self.text.len() {
             self.cursor.row = row + 1;
-            let next_line_len = self.text[row + 1].chars().count();
-            if col > next_line_len {
-                self.cursor.col = next_line_len
+            self.cursor.col = match visual {
+                true => self.latched_col_in(row + 1, &self.line_rows(row + 1), 0),
+                false => self.latched_col(row + 1),
             }
-            self.cursor.col = min(next_line_len, self.cursor.latch_col)
         }
     }
 
     fn move_cursor_up(&mut self) {
-        let (row, col) = (self.cursor.row, self.cursor.col);
+        let row = self.cursor.row;
+        let visual = self.vertical_movement == VerticalMovement::Visual;
+        let rows = self.line_rows(row);
+        let sub = layout::row_of(&rows, self.cursor.col);
 
-        if row > 0 {
+        if visual && sub > 0 {
+            self.cursor.col = self.latched_col_in(row, &rows, sub - 1)
+        } else if row > 0 {
             self.cursor.row = row - 1;
-            let prev_line_len = self.text[row - 1].chars().count();
-            if col > prev_line_len {
-                self.cursor.col = prev_line_len
+            self.cursor.col = match visual {
+                true => {
+                    let rows = self.line_rows(row - 1);
+                    self.latched_col_in(row - 1, &rows, rows.len() - 1)
+                }
+                false => self.latched_col(row - 1),
             }
-            self.cursor.col = min(prev_line_len, self.cursor.latch_col)
         }
     }
 
     fn insert_char(&mut self, ch: char) {
+        self.edit(
+            EditKind::Insert,
+            self.cursor.row,
+            self.cursor.col,
+            ch.to_string(),
+        );
+    }
+
+    // a list item goes on with the next bullet on the new line, and Enter on
+    // an item with no text removes its bullet to end the list
+    fn insert_newline(&mut self) {
         let (row, col) = (self.cursor.row, self.cursor.col);
-        if self.text.is_empty() {
-            self.text.push(String::new());
+        let line = self.text.line(row).into_owned();
+        let byte = graphemes::byte_index(&line, col);
+        if let Some(bullet) = tasks::empty_item(&line).filter(|_| byte == line.len()) {
+            let text = line[bullet.clone()].to_string();
+            self.edit_at_byte(EditKind::Delete, row, bullet.start, text);
+            return;
         }
+        self.edit(EditKind::Insert, row, col, "\n".into());
+        if let Some(prefix) = tasks::continuation(&line, byte) {
+            self.history.join = true;
+            self.edit(EditKind::Insert, row + 1, 0, prefix);
+        }
+    }
 
-        let curr_line = &mut self.text[row];
-        curr_line.insert(col, ch);
+    // check or uncheck the task on the cursor line as one undo step, making
+    // the line a task if it isn't one
+    pub fn toggle_task(&mut self) {
+        let row = self.cursor.row;
+        let line = self.text.line(row).into_owned();
+        let (range, replacement) = tasks::toggle(&line);
+        let cursor = graphemes::byte_index(&line, self.cursor.col);
 
-        if col == curr_line.char_indices().count() {
-            curr_line.insert(col + 1, ' ');
+        if !range.is_empty() {
+            let text = line[range.clone()].to_string();
+            self.edit_at_byte(EditKind::Delete, row, range.start, text);
+            self.history.join = true;
         }
-        self.move_cursor_right()
-    }
+        self.edit_at_byte(EditKind::Insert, row, range.start, replacement.into());
+        self.history.seal();
 
-    fn insert_newline(&mut self) {
-        let (row, col) = (self.cursor.row, self.cursor.col);
-        let line = &mut self.text[row];
-
-        // we could be in the middle of a line of text
-        let pos_in_line = line
-            .char_indices()
-            .nth(col)
-            .map(|(i, _)| i)
-            .unwrap_or(line.len());
-        let newline: String = line[pos_in_line..].to_string();
-        line.truncate(pos_in_line);
-        self.text.insert(row + 1, newline);
-        self.cursor.row = row.saturating_add(1);
-        self.cursor.col = 0;
+        // the cursor stays on the same text
+        let byte = match cursor >= range.end {
+            true => cursor + replacement.len() - range.len(),
+            false => cursor.min(range.start),
+        };
+        let col = graphemes::col_index(&self.text.line(row), byte);
+        self.set_cursor((row, col));
+        if let Some(step) = self.history.undo.back_mut() {
+            step.after = (row, col);
+        }
     }
 
     fn delete_char(&mut self) {
         let (row, col) = (self.cursor.row, self.cursor.col);
         if col == 0 {
             self.delete_line();
-        } else {
-            let line = &mut self.text[row];
-            line.remove(col - 1);
-            self.cursor.col = col.saturating_sub(1);
+        } else if let Some(grapheme) = graphemes::nth(&self.text.line(row), col - 1) {
+            self.edit(EditKind::Delete, row, col - 1, grapheme.to_string());
         }
     }
 
+    // join the current line onto the end of the previous one
     fn delete_line(&mut self) {
-        let (row, _) = (self.cursor.row, self.cursor.col);
+        let row = self.cursor.row;
         if row == 0 {
             return;
         }
+        let prev_len = self.line_len(row - 1);
+        self.edit(EditKind::Delete, row - 1, prev_len, "\n".into());
+    }
 
-        let curr_line = self.text.remove(row);
-        let prev_line = &mut self.text[row - 1];
-
-        self.cursor.row = row.saturating_sub(1);
-        self.cursor.col = prev_line.chars().count();
+    // apply an edit at the cursor and record it for undo
+    fn edit(&mut self, kind: EditKind, row: usize, col: usize, text: String) {
+        let byte = graphemes::byte_index(&self.text.line(row), col);
+        self.edit_at_byte(kind, row, byte, text);
+    }
 
-        prev_line.push_str(&curr_line);
+    fn edit_at_byte(&mut self, kind: EditKind, row: usize, byte: usize, text: String) {
+        let edit = Edit {
+            kind,
+            row,
+            byte,
+            text,
+        };
+        // any change ends the selection, even an empty one
+        self.anchor = None;
+        let before = (self.cursor.row, self.cursor.col);
+        let after = self.apply(&edit);
+        self.set_cursor(after);
+        self.history.record(edit, before, after);
     }
 
-    fn move_cursor_next_word(&mut self) {
-        let (row, col) = (self.cursor.row, self.cursor.col);
-        let line = &self.text[row];
+    // returns where the cursor ends up after the edit
+    fn apply(&mut self, edit: &Edit) -> (usize, usize) {
+        match edit.kind {
+            EditKind::Insert => self.insert_text(edit.row, edit.byte, &edit.text),
+            EditKind::Delete => {
+                self.remove_text(edit.row, edit.byte, &edit.text);
+                (
+                    edit.row,
+                    graphemes::col_index(&self.text.line(edit.row), edit.byte),
+                )
+            }
+        }
+    }
 
-        fn next_word_start(line: &String, init_pos: usize) -> Option<usize> {
-            for (i, ch) in line.chars().enumerate().skip(init_pos) {
-                if ch.is_whitespace() {
-                    return Some(i);
-                }
+    fn revert(&mut self, edit: &Edit) {
+        match edit.kind {
+            EditKind::Insert => self.remove_text(edit.row, edit.byte, &edit.text),
+            EditKind::Delete => {
+                self.insert_text(edit.row, edit.byte, &edit.text);
             }
-            None
         }
+    }
 
-        match next_word_start(line, col + 1) {
-            Some(col) => self.cursor.col = col,
-            None if row < self.text.len() - 1 => {
-                self.cursor.row = row + 1;
-                self.cursor.col = 0;
+    pub fn undo(&mut self) {
+        let Some(step) = self.history.undo.pop_back() else {
+            return;
+        };
+        for edit in step.edits.iter().rev() {
+            self.revert(edit);
+        }
+        self.anchor = None;
+        self.set_cursor(step.before);
+        self.history.redo.push(step);
+        self.history.seal();
+    }
+
+    pub fn redo(&mut self) {
+        let Some(step) = self.history.redo.pop() else {
+            return;
+        };
+        for edit in &step.edits {
+            self.apply(edit);
+        }
+        self.anchor = None;
+        self.set_cursor(step.after);
+        self.history.undo.push_back(step);
+        self.history.seal();
+    }
+
+    // insert text at a byte offset, returning the position right after it
+    fn insert_text(&mut self, row: usize, byte: usize, text: &str) -> (usize, usize) {
+        self.text.insert((row, byte), text);
+        self.line_states.truncate(row + 1);
+        self.revision += 1;
+        match text.rsplit_once('\n') {
+            Some((head, last)) => {
+                let end_row = row + head.matches('\n').count() + 1;
+                (
+                    end_row,
+                    graphemes::col_index(&self.text.line(end_row), last.len()),
+                )
             }
-            None => self.cursor.col = line.len(),
+            None => (
+                row,
+                graphemes::col_index(&self.text.line(row), byte + text.len()),
+            ),
+        }
+    }
+
+    fn text_between(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let byte = |(row, col): Pos| (row, graphemes::byte_index(&self.text.line(row), col));
+        self.text.slice(byte(start), byte(end))
+    }
+
+    // remove `text`, which starts at a byte offset of a row
+    fn remove_text(&mut self, row: usize, byte: usize, text: &str) {
+        self.text.remove((row, byte), text);
+        self.line_states.truncate(row + 1);
+        self.revision += 1;
+    }
+
+    fn set_cursor(&mut self, (row, col): (usize, usize)) {
+        self.cursor.row = row;
+        self.cursor.col = col;
+        self.latch();
+    }
+
+    fn move_cursor_next_word(&mut self) {
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        let next_blank = self
+            .text
+            .line(row)
+            .graphemes(true)
+            .enumerate()
+            .skip(col + 1)
+            .find(|(_, grapheme)| graphemes::is_blank(grapheme))
+            .map(|(col, _)| col);
+
+        match next_blank {
+            Some(col) => self.set_cursor((row, col)),
+            None if row + 1 < self.text.len() => self.set_cursor((row + 1, 0)),
+            None => self.set_cursor((row, self.line_len(row))),
         }
     }
 
     fn move_cursor_prev_word(&mut self) {
         let (row, col) = (self.cursor.row, self.cursor.col);
-        let line = &self.text[row];
+        let prev_blank = self
+            .text
+            .line(row)
+            .graphemes(true)
+            .take(col)
+            .enumerate()
+            .filter(|(_, grapheme)| graphemes::is_blank(grapheme))
+            .last()
+            .map(|(col, _)| col);
 
-        fn prev_word_end(line: &String, init_pos: usize) -> Option<usize> {
-            for (i, ch) in line.chars().rev().enumerate().skip(line.len() - init_pos) {
-                if ch.is_whitespace() {
-                    return Some(line.len() - i - 1);
-                }
-            }
-            None
+        match prev_blank {
+            Some(col) => self.set_cursor((row, col)),
+            None if row > 0 => self.set_cursor((row - 1, self.line_len(row - 1))),
+            None => self.set_cursor((row, 0)),
         }
+    }
 
-        match prev_word_end(line, col) {
-            Some(col) => self.cursor.col = col,
-            None if row > 0 => {
-                self.cursor.row = row - 1;
-                self.cursor.col = self.text[row - 1].len();
+    // the highlighter state at the start of a row, carried on from the
+    // nearest one kept
+    fn line_state(&self, row: usize) -> LineState {
+        let Some(highlighter) = &self.highlighter else {
+            return LineState::default();
+        };
+        let from = min(row, self.line_states.len().saturating_sub(1));
+        let mut state = self.line_states.get(from).cloned().unwrap_or_default();
+        for row in from..row {
+            highlighter.highlight(&self.text.line(row), &mut state);
+        }
+        state
+    }
+
+    fn keep_line_states(&mut self, row: usize) {
+        let Some(highlighter) = self.highlighter.clone() else {
+            return;
+        };
+        if self.line_states.is_empty() {
+            self.line_states.push(LineState::default());
+        }
+        while self.line_states.len() <= row {
+            let last = self.line_states.len() - 1;
+            let mut state = self.line_states[last].clone();
+            match row - last > SYNC_LINES {
+                true => highlighter.skip(&self.text.line(last), &mut state),
+                false => {
+                    highlighter.highlight(&self.text.line(last), &mut state);
+                }
             }
-            None => self.cursor.col = 0,
+            self.line_states.push(state);
         }
     }
 
+    // scroll just enough to keep the cursor on screen
     fn update_visible_lines(&mut self) {
-        let row = self.cursor.row;
-        let available_height = self.terminal_height - HEIGHT_PADDING - 1;
-        let start = if row >= available_height {
-            row.saturating_sub(available_height) + 1
-        } else {
-            0
+        let top = min(self.scroll.0, self.text.len().saturating_sub(1));
+        let sub = match top == self.scroll.0 {
+            true => min(self.scroll.1, self.line_rows(top).len() - 1),
+            false => 0,
         };
-        let end = min(self.text.len(), start + available_height);
+        self.scroll = (top, sub);
+
+        let (sub, x) = self.cursor_visual();
+        let cursor = (self.cursor.row, sub);
+        if cursor < self.scroll {
+            self.scroll = cursor;
+        } else {
+            // walk up from the cursor, if the screen fills first scroll down
+            let mut top = cursor;
+            for _ in 1..self.height() {
+                if top <= self.scroll {
+                    break;
+                }
+                top = self.prev_visual(top);
+            }
+            if top > self.scroll {
+                self.scroll = top;
+            }
+        }
+
+        // unwrapped lines scroll sideways to follow the cursor
+        if let (WrapMode::None, Some((width, _))) = (self.wrap_mode, self.viewport) {
+            let width = width.max(1);
+            let line = &*self.text.line(self.cursor.row);
+            let cells = graphemes::nth(line, self.cursor.col).map_or(1, |g| g.width().max(1));
+            if x < self.hscroll {
+                self.hscroll = x;
+            } else if x + cells > self.hscroll + width {
+                self.hscroll = (x + cells).saturating_sub(width);
+            }
+        }
+        self.keep_line_states(self.scroll.0);
+    }
+
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    // the line at the top of the screen
+    pub fn top_row(&self) -> usize {
+        self.scroll.0
+    }
 
-        self.visible_lines = (start, end);
+    // scroll a line to the top of the screen, the cursor moving along only if
+    // it would be left off screen
+    pub fn scroll_to(&mut self, row: usize) {
+        let row = min(row, self.text.len().saturating_sub(1));
+        self.scroll = (row, 0);
+        let mut bottom = row;
+        let mut rows = self.line_rows(row).len();
+        while bottom + 1 < self.text.len() {
+            rows += self.line_rows(bottom + 1).len();
+            if rows > self.height() {
+                break;
+            }
+            bottom += 1;
+        }
+        let at = self.cursor.row.clamp(row, bottom);
+        if at != self.cursor.row {
+            let col = self.latched_col(at);
+            self.cursor.row = at;
+            self.cursor.col = col;
+        }
+        self.update_visible_lines();
+    }
+
+    // place the cursor at a position, clamped to the text
+    pub fn move_cursor_to(&mut self, row: usize, col: usize) {
+        let row = min(row, self.text.len().saturating_sub(1));
+        self.set_cursor((row, min(col, self.line_len(row))));
+        self.update_visible_lines();
+    }
+
+    // replace the text with a note's content, starting a fresh history
+    pub fn load(&mut self, text: Vec<String>) {
+        self.text = Buffer::from(text);
+        self.line_states.clear();
+        self.revision += 1;
+        self.anchor = None;
+        self.history.clear();
+        if let Keymap::Vim(vim) = &mut self.keymap {
+            vim.reset();
+        }
+        self.move_cursor_to(0, 0);
     }
 
     pub fn reset(&mut self) {
-        self.text.clear();
+        self.text = Buffer::default();
+        self.line_states.clear();
+        self.revision += 1;
+        self.anchor = None;
+        self.history.clear();
         self.cursor.row = 0;
         self.cursor.col = 0;
+        self.scroll = (0, 0);
+        self.hscroll = 0;
     }
 }
 
-fn line_into_spans(line: &str) -> Vec<Span> {
-    let mut spans = Vec::new();
-
-    for ch in line.chars() {
-        let span = Span::styled(ch.to_string(), Style::default());
-        spans.push(span);
-    }
-
-    spans
+fn selection_style() -> Style {
+    Style::default().bg(Color::Blue).fg(Color::White)
 }
 
-fn cursor_line_into_spans(line: &str, cursor_pos: usize) -> Vec<Span> {
-    let mut spans = Vec::new();
+// what a line's columns are drawn with
+struct LineStyle {
+    // from the highlighter, as column ranges
+    syntax: Vec<(Range<usize>, Style)>,
+    cursor: Option<usize>,
+    // `usize::MAX` as the end when the line break is selected too
+    selected: Option<(usize, usize)>,
+    found: Vec<(usize, usize)>,
+}
 
-    for (i, ch) in line.chars().enumerate() {
-        let style = if i == cursor_pos {
+impl LineStyle {
+    fn at(&self, col: usize) -> Style {
+        let within = |(start, end): (usize, usize)| start <= col && col < end;
+        if self.cursor == Some(col) {
             Style::default().bg(Color::Gray).fg(Color::Black)
+        } else if self.selected.is_some_and(within) {
+            selection_style()
+        } else if let Some(&(start, _)) = self.found.iter().find(|&&found| within(found)) {
+            // the match the cursor is on stands out from the others
+            let bg = match self.cursor == Some(start) {
+                true => Color::LightYellow,
+                false => Color::Yellow,
+            };
+            Style::default().bg(bg).fg(Color::Black)
         } else {
-            Style::default()
-        };
-        let span = Span::styled(ch.to_string(), style);
-        spans.push(span);
+            self.syntax
+                .iter()
+                .filter(|(range, _)| range.contains(&col))
+                .fold(Style::default(), |style, &(_, syntax)| style.patch(syntax))
+        }
+    }
+}
+
+// spans for the columns `start..end` of a line, one screen row, with a span
+// per run of equally styled text. `last` is set on the row that ends the line
+fn row_into_spans(
+    line: &str,
+    (start, end): (usize, usize),
+    style: &LineStyle,
+    last: bool,
+) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = Vec::new();
+
+    for (i, grapheme) in line.graphemes(true).enumerate().take(end).skip(start) {
+        let style = style.at(i);
+        match spans.last_mut() {
+            Some(span) if span.style == style => span.content.to_mut().push_str(grapheme),
+            _ => spans.push(Span::styled(grapheme.to_string(), style)),
+        }
     }
 
-    if cursor_pos >= line.len() {
+    if !last {
+        return spans;
+    }
+    if style.cursor.is_some_and(|col| col >= graphemes::len(line)) {
         spans.push(Span::styled(
-            "N".to_string(),
+            "N",
             Style::default().bg(Color::Gray).fg(Color::Gray),
         ));
+    } else if style.selected.is_some_and(|(_, end)| end == usize::MAX) {
+        // show the selected line break
+        spans.push(Span::styled(" ", selection_style()));
     }
 
     spans
 }
 
-impl Widget for TextBox {
-    fn render(mut self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        self.update_visible_lines();
-        let (start, end) = self.visible_lines;
-        let visible_text = &self.text[start..end];
+impl Widget for &TextBox {
+    fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
+        let height = area.height.saturating_sub(2) as usize;
+        let width = area.width.saturating_sub(2) as usize;
+        let cursor_sub = self.cursor_visual().0;
         let mut lines: Vec<Line> = Vec::new();
 
-        for (i, line) in visible_text.iter().enumerate() {
-            let spans: Vec<Span>;
-            if i+start == self.cursor.row {
-                spans = cursor_line_into_spans(line, self.cursor.col);
-            } else {
-                spans = line_into_spans(line);
+        let (mut row, mut sub) = self.scroll;
+        let mut state = self.line_state(row);
+        while lines.len() < height && row < self.text.len() {
+            let line = self.text.line(row);
+            let syntax = match &self.highlighter {
+                Some(highlighter) => highlighter.highlight(&line, &mut state),
+                None => Vec::new(),
+            };
+            let col = |byte| graphemes::col_index(&line, byte);
+            let mut style = LineStyle {
+                syntax: syntax
+                    .into_iter()
+                    .map(|(range, style)| (col(range.start)..col(range.end), style))
+                    .collect(),
+                cursor: None,
+                selected: self.selected_cols(row),
+                found: self.highlighted(&line),
+            };
+            // without wrapping only what fits past the sideways scroll, a wide
+            // char cut in half by it is left blank
+            let mut pad = 0;
+            let rows = match self.wrap_mode {
+                WrapMode::Soft => self.line_rows(row),
+                WrapMode::None => {
+                    let mut start = graphemes::col_at_width(&line, self.hscroll);
+                    if graphemes::width_to(&line, start) < self.hscroll {
+                        start += 1;
+                        pad = 1;
+                    }
+                    let shown = &line[graphemes::byte_index(&line, start)..];
+                    let end = start + graphemes::col_at_width(shown, width.saturating_sub(pad));
+                    vec![(start, end)]
+                }
+            };
+
+            for (i, &cols) in rows.iter().enumerate().skip(sub) {
+                if lines.len() == height {
+                    break;
+                }
+                style.cursor =
+                    (row == self.cursor.row && i == cursor_sub).then_some(self.cursor.col);
+                let last = i + 1 == rows.len();
+                let mut spans = vec![Span::raw(" ".repeat(pad))];
+                spans.extend(row_into_spans(&line, cols, &style, last));
+                lines.push(Line::from(spans));
             }
-            lines.push(Line::from(spans));
+            row += 1;
+            sub = 0;
         }
+
         Paragraph::new(lines)
             .block(Block::default().borders(Borders::ALL))
-            .wrap(Wrap { trim: false })
             .render(area, buf);
     }
 }
@@ -368,4 +1220,549 @@ mod tests {
         textbox.move_cursor_up();
         assert_eq!(textbox.cursor.row, 0);
     }
+
+    #[test]
+    fn test_move_cursor_to_clamps() {
+        let text = vec!["Lorem ipsum".into(), "dolor".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(1, 3);
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 3));
+
+        textbox.move_cursor_to(5, 50);
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 5));
+        assert_eq!(textbox.cursor.latch_col, 5);
+    }
+
+    fn type_str(textbox: &mut TextBox, input: &str) {
+        for ch in input.chars() {
+            let key = match ch {
+                '\n' => KeyCode::Enter,
+                '\x08' => KeyCode::Backspace,
+                ch => KeyCode::Char(ch),
+            };
+            textbox.handle_input(key, KeyModifiers::NONE);
+        }
+    }
+
+    // the default keybindings for the editing commands
+    fn ctrl(textbox: &mut TextBox, ch: char) {
+        let command = match ch {
+            'z' => EditCommand::Undo,
+            'y' => EditCommand::Redo,
+            'c' => EditCommand::Copy,
+            'x' => EditCommand::Cut,
+            _ => EditCommand::Paste,
+        };
+        textbox.command(command);
+    }
+
+    #[test]
+    fn test_undo_coalesces_typing_by_word() {
+        let mut textbox = TextBox::new(20);
+        type_str(&mut textbox, "hello world");
+        assert_eq!(textbox.text, vec!["hello world".to_string()]);
+
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["hello".to_string()]);
+        assert_eq!(textbox.cursor.col, 5);
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec![String::new()]);
+
+        ctrl(&mut textbox, 'y');
+        ctrl(&mut textbox, 'y');
+        assert_eq!(textbox.text, vec!["hello world".to_string()]);
+        assert_eq!(textbox.cursor.col, 11);
+    }
+
+    #[test]
+    fn test_undo_backspace_across_line_join() {
+        let text = vec!["Lorem ipsum".into(), "dolor".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(1, 0);
+        type_str(&mut textbox, "\x08");
+        assert_eq!(textbox.text, vec!["Lorem ipsumdolor".to_string()]);
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 11));
+
+        ctrl(&mut textbox, 'z');
+        assert_eq!(
+            textbox.text,
+            vec!["Lorem ipsum".to_string(), "dolor".into()]
+        );
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 0));
+    }
+
+    #[test]
+    fn test_cursor_moves_split_undo_steps_and_edits_clear_redo() {
+        let text = vec!["ab".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 2);
+        type_str(&mut textbox, "\x08");
+        textbox.handle_input(KeyCode::Left, KeyModifiers::NONE);
+        type_str(&mut textbox, "xé\n");
+        assert_eq!(textbox.text, vec!["xé".to_string(), "a".into()]);
+
+        ctrl(&mut textbox, 'z');
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["a".to_string()]);
+        type_str(&mut textbox, "c");
+        ctrl(&mut textbox, 'y');
+        assert_eq!(textbox.text, vec!["ca".to_string()]);
+
+        ctrl(&mut textbox, 'z');
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["ab".to_string()]);
+        assert_eq!(textbox.cursor.col, 2);
+    }
+
+    #[test]
+    fn test_undo_history_is_bounded() {
+        let mut textbox = TextBox::new(20);
+        for _ in 0..UNDO_LIMIT + 10 {
+            type_str(&mut textbox, "\n");
+        }
+        for _ in 0..UNDO_LIMIT + 10 {
+            ctrl(&mut textbox, 'z');
+        }
+        assert_eq!(textbox.text.len(), 11);
+    }
+
+    fn shift(textbox: &mut TextBox, key: KeyCode, times: usize) {
+        for _ in 0..times {
+            textbox.handle_input(key, KeyModifiers::SHIFT);
+        }
+    }
+
+    #[test]
+    fn test_copy_paste_across_lines() {
+        let text = vec!["Lorem ipsum".into(), "dolor sit".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 6);
+        shift(&mut textbox, KeyCode::Down, 1);
+        shift(&mut textbox, KeyCode::Left, 1);
+        assert_eq!(textbox.selection(), Some(((0, 6), (1, 5))));
+        assert_eq!(textbox.selected_cols(0), Some((6, usize::MAX)));
+
+        ctrl(&mut textbox, 'c');
+        assert_eq!(textbox.register, "ipsum\ndolor");
+
+        textbox.handle_input(KeyCode::Right, KeyModifiers::NONE);
+        assert!(textbox.selection().is_none());
+        ctrl(&mut textbox, 'v');
+        assert_eq!(
+            textbox.text,
+            vec![
+                "Lorem ipsum".to_string(),
+                "dolor ipsum".into(),
+                "dolorsit".into()
+            ]
+        );
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (2, 5));
+    }
+
+    #[test]
+    fn test_backtab_selects_back_a_word() {
+        let text = vec!["Lorem ipsum dolor".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 12);
+        shift(&mut textbox, KeyCode::Right, 2);
+        assert_eq!(textbox.selection(), Some(((0, 12), (0, 14))));
+
+        // the selection keeps its anchor and grows back over ` ipsum `
+        textbox.handle_input(KeyCode::BackTab, KeyModifiers::SHIFT);
+        textbox.handle_input(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(textbox.selection(), Some(((0, 5), (0, 12))));
+        ctrl(&mut textbox, 'c');
+        assert_eq!(textbox.register, " ipsum ");
+    }
+
+    #[test]
+    fn test_typing_replaces_selection_in_one_undo_step() {
+        let text = vec!["Lorem ipsum".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        shift(&mut textbox, KeyCode::Right, 5);
+        type_str(&mut textbox, "Hi");
+        assert_eq!(textbox.text, vec!["Hi ipsum".to_string()]);
+
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["Lorem ipsum".to_string()]);
+        assert_eq!(textbox.cursor.col, 5);
+    }
+
+    #[test]
+    fn test_cut_and_delete_selection() {
+        let text = vec!["Lorem ipsum".into(), "dolor".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 5);
+        shift(&mut textbox, KeyCode::Down, 1);
+        ctrl(&mut textbox, 'x');
+        assert_eq!(textbox.text, vec!["Lorem".to_string()]);
+        assert_eq!(textbox.register, " ipsum\ndolor");
+
+        ctrl(&mut textbox, 'z');
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 5));
+        textbox.move_cursor_to(0, 5);
+        shift(&mut textbox, KeyCode::Left, 2);
+        type_str(&mut textbox, "\x08");
+        assert_eq!(textbox.text, vec!["Lor ipsum".to_string(), "dolor".into()]);
+    }
+
+    #[test]
+    fn test_enter_continues_lists() {
+        let mut textbox = TextBox::new(20);
+        // Enter on the empty item ends the list
+        type_str(&mut textbox, "- [x] one\ntwo\n\nthree");
+        assert_eq!(
+            textbox.text,
+            vec!["- [x] one".to_string(), "- [ ] two".into(), "three".into()]
+        );
+
+        // the bullet comes back with the line break in one undo step
+        textbox.load(vec!["1. one".into()]);
+        textbox.move_cursor_to(0, 6);
+        type_str(&mut textbox, "\n");
+        assert_eq!(textbox.text, vec!["1. one".to_string(), "2. ".into()]);
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["1. one".to_string()]);
+    }
+
+    #[test]
+    fn test_toggle_task_keeps_cursor_on_text() {
+        let mut textbox = TextBox::from(vec!["  buy milk".to_string()]);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 6);
+        textbox.command(EditCommand::ToggleTask);
+        assert_eq!(textbox.text, vec!["  - [ ] buy milk".to_string()]);
+        assert_eq!(textbox.cursor.col, 12);
+
+        textbox.command(EditCommand::ToggleTask);
+        assert_eq!(textbox.text, vec!["  - [x] buy milk".to_string()]);
+        assert_eq!(textbox.cursor.col, 12);
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["  - [ ] buy milk".to_string()]);
+        ctrl(&mut textbox, 'y');
+        assert_eq!(textbox.text, vec!["  - [x] buy milk".to_string()]);
+        assert_eq!(textbox.cursor.col, 12);
+    }
+
+    #[test]
+    fn test_insert_str_is_one_edit() {
+        let text = vec!["Lorem ipsum".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.terminal_height = 20;
+        textbox.move_cursor_to(0, 6);
+        textbox.insert_str("one\r\ntwo\nthree ");
+        assert_eq!(
+            textbox.text,
+            vec!["Lorem one".to_string(), "two".into(), "three ipsum".into()]
+        );
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (2, 6));
+
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec!["Lorem ipsum".to_string()]);
+    }
+
+    #[test]
+    fn test_graphemes_and_wide_chars() {
+        let mut textbox = TextBox::new(20);
+        type_str(&mut textbox, "e\u{301}界👍\nab");
+        assert_eq!(textbox.text.line(0), "e\u{301}界👍");
+        assert_eq!(textbox.line_len(0), 3);
+
+        // up keeps the screen column, the wide char takes two cells
+        textbox.move_cursor_to(1, 2);
+        textbox.move_cursor_up();
+        assert_eq!(textbox.cursor.col, 1);
+        textbox.move_cursor_right();
+        textbox.move_cursor_down();
+        assert_eq!(textbox.cursor.col, 2);
+
+        textbox.move_cursor_to(0, 3);
+        type_str(&mut textbox, "\x08\x08");
+        assert_eq!(textbox.text.line(0), "e\u{301}");
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text.line(0), "e\u{301}界👍");
+    }
+
+    #[test]
+    fn test_combining_mark_joins_previous_char() {
+        let mut textbox = TextBox::new(20);
+        type_str(&mut textbox, "ae");
+        textbox.handle_input(KeyCode::Char('\u{301}'), KeyModifiers::NONE);
+        assert_eq!(textbox.line_len(0), 2);
+        assert_eq!(textbox.cursor.col, 2);
+        type_str(&mut textbox, "\x08");
+        assert_eq!(textbox.text.line(0), "a");
+        ctrl(&mut textbox, 'z');
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.text, vec![String::new()]);
+    }
+
+    #[test]
+    fn test_up_and_down_move_by_wrapped_row() {
+        let mut textbox = TextBox::from(vec!["one two three four".into(), "five".into()]);
+        // rows of 8 cells: "one two ", "three ", "four"
+        textbox.set_viewport(9, 10);
+        textbox.move_cursor_to(0, 2);
+        textbox.move_cursor_down();
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 10));
+        textbox.move_cursor_down();
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 16));
+        textbox.move_cursor_down();
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 2));
+        textbox.move_cursor_up();
+        textbox.move_cursor_up();
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 10));
+
+        // past the end of a wrapped row stops before the next one starts
+        textbox.move_cursor_to(0, 7);
+        textbox.move_cursor_down();
+        assert_eq!(textbox.cursor.col, 13);
+
+        textbox.set_vertical_movement(VerticalMovement::Logical);
+        textbox.move_cursor_to(0, 2);
+        textbox.move_cursor_down();
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 2));
+    }
+
+    #[test]
+    fn test_scroll_counts_wrapped_rows() {
+        let text = vec!["aaaa bbbb cccc".into(), "x".into(), "y".into(), "z".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.set_viewport(6, 3);
+        // the first line takes three rows, so "x" scrolls the screen
+        textbox.move_cursor_to(1, 0);
+        assert_eq!(textbox.scroll, (0, 1));
+        textbox.move_cursor_to(3, 0);
+        assert_eq!(textbox.scroll, (1, 0));
+        // moving back up only scrolls when the cursor leaves the screen
+        textbox.move_cursor_to(2, 0);
+        assert_eq!(textbox.scroll, (1, 0));
+        textbox.handle_input(KeyCode::Up, KeyModifiers::NONE);
+        textbox.handle_input(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(textbox.scroll, (0, 2));
+    }
+
+    #[test]
+    fn test_scroll_to_takes_cursor_along() {
+        let text: Vec<String> = (0..10).map(|i| format!("line {i}")).collect();
+        let mut textbox = TextBox::from(text);
+        textbox.set_viewport(10, 3);
+        textbox.move_cursor_to(1, 4);
+        // the cursor stays put while it is on screen
+        textbox.scroll_to(1);
+        assert_eq!((textbox.top_row(), textbox.cursor.row), (1, 1));
+        textbox.scroll_to(6);
+        assert_eq!((textbox.top_row(), textbox.cursor.row), (6, 6));
+        assert_eq!(textbox.cursor.col, 4);
+        textbox.scroll_to(2);
+        assert_eq!((textbox.top_row(), textbox.cursor.row), (2, 4));
+    }
+
+    #[test]
+    fn test_no_wrap_scrolls_sideways() {
+        let mut textbox = TextBox::from(vec!["abcdefghij".into()]);
+        textbox.set_wrap_mode(WrapMode::None);
+        textbox.set_viewport(4, 3);
+        textbox.move_cursor_to(0, 6);
+        assert_eq!(textbox.hscroll, 3);
+        textbox.move_cursor_to(0, 1);
+        assert_eq!(textbox.hscroll, 1);
+    }
+
+    #[test]
+    fn test_edits_update_kept_line_states() {
+        let text = vec!["```".into(), "# code".into(), "```".into(), "# text".into()];
+        let mut textbox = TextBox::from(text);
+        textbox.set_highlighter(crate::highlight::for_syntax(Default::default()));
+        textbox.keep_line_states(3);
+        assert_eq!(textbox.line_state(3), LineState::Text);
+        assert!(matches!(textbox.line_state(2), LineState::Block { .. }));
+
+        // removing the closing fence leaves the rest of the note in the block
+        textbox.move_cursor_to(2, 3);
+        type_str(&mut textbox, "\x08\x08\x08");
+        assert!(matches!(textbox.line_state(3), LineState::Block { .. }));
+        ctrl(&mut textbox, 'z');
+        assert_eq!(textbox.line_state(3), LineState::Text);
+    }
+
+    mod props {
+        use super::super::*;
+        use crate::config::EditingMode;
+        use proptest::prelude::*;
+
+        // accents written as combining marks, wide CJK, skin tones, ZWJ and
+        // flag halves, which all change how chars group into graphemes
+        const CHARS: &[char] = &[
+            'a',
+            ' ',
+            'é',
+            'e',
+            '\u{301}',
+            '界',
+            '👍',
+            '\u{1f3fd}',
+            '\u{200d}',
+            '🇳',
+            '🇴',
+        ];
+
+        #[derive(Clone, Debug)]
+        enum Op {
+            Key(KeyCode, KeyModifiers),
+            Command(EditCommand),
+            Insert(String),
+        }
+
+        fn typed() -> impl Strategy<Value = String> {
+            prop::collection::vec(prop::sample::select(CHARS), 0..8)
+                .prop_map(|chars| chars.into_iter().collect())
+        }
+
+        fn op() -> impl Strategy<Value = Op> {
+            use KeyCode::*;
+            let keys = vec![Enter, Backspace, Left, Right, Up, Down, Tab, BackTab];
+            let moves = vec![Left, Right, Up, Down];
+            let commands = vec![
+                EditCommand::Undo,
+                EditCommand::Redo,
+                EditCommand::Copy,
+                EditCommand::Cut,
+                EditCommand::Paste,
+                EditCommand::ToggleTask,
+            ];
+            prop_oneof![
+                6 => prop::sample::select(CHARS).prop_map(|ch| Op::Key(Char(ch), KeyModifiers::NONE)),
+                3 => prop::sample::select(keys).prop_map(|key| Op::Key(key, KeyModifiers::NONE)),
+                1 => prop::sample::select(moves).prop_map(|key| Op::Key(key, KeyModifiers::SHIFT)),
+                1 => prop::sample::select(commands).prop_map(Op::Command),
+                1 => typed().prop_map(|text| Op::Insert(text.replace(' ', "\n"))),
+            ]
+        }
+
+        fn run(textbox: &mut TextBox, op: &Op) {
+            match op {
+                Op::Key(key, modifiers) => textbox.handle_input(*key, *modifiers),
+                Op::Command(command) => textbox.command(*command),
+                Op::Insert(text) => textbox.insert_str(text),
+            }
+        }
+
+        fn check_positions(textbox: &TextBox) {
+            assert!(textbox.text.lines().all(|line| !line.contains('\n')));
+            let valid = |(row, col): Pos| row < textbox.text.len() && col <= textbox.line_len(row);
+            if !textbox.text.is_empty() {
+                assert!(valid((textbox.cursor.row, textbox.cursor.col)));
+            }
+            if let Some(anchor) = textbox.anchor {
+                assert!(valid(anchor));
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn typing_builds_the_typed_text(text in typed()) {
+                let mut textbox = TextBox::new(20);
+                for ch in text.chars() {
+                    textbox.handle_input(KeyCode::Char(ch), KeyModifiers::NONE);
+                }
+                prop_assert_eq!(textbox.text.to_lines().join("\n"), text.clone());
+                prop_assert_eq!(textbox.cursor.col, graphemes::len(&text));
+            }
+
+            #[test]
+            fn edits_stay_in_bounds_and_undo_restores(
+                lines in prop::collection::vec(typed(), 1..4),
+                ops in prop::collection::vec(op(), 0..60),
+            ) {
+                let mut textbox = TextBox::new(20);
+                textbox.load(lines.clone());
+                for op in &ops {
+                    run(&mut textbox, op);
+                    check_positions(&textbox);
+                }
+
+                let edited = textbox.text.to_lines();
+                let steps = textbox.history.undo.len();
+                for _ in 0..steps {
+                    textbox.undo();
+                    check_positions(&textbox);
+                }
+                prop_assert_eq!(&textbox.text, &lines);
+                for _ in 0..steps {
+                    textbox.redo();
+                }
+                prop_assert_eq!(&textbox.text, &edited);
+            }
+
+            #[test]
+            fn cursor_stays_on_screen_when_wrapped(
+                lines in prop::collection::vec(typed(), 1..4),
+                ops in prop::collection::vec(op(), 0..60),
+                width in 1usize..6,
+                height in 1usize..4,
+            ) {
+                let mut textbox = TextBox::new(20);
+                textbox.load(lines);
+                textbox.set_viewport(width, height);
+                for op in &ops {
+                    run(&mut textbox, op);
+                    check_positions(&textbox);
+
+                    // count screen rows from the top of the screen to the cursor
+                    let (sub, _) = textbox.cursor_visual();
+                    let (mut at, mut shown) = ((textbox.cursor.row, sub), 1);
+                    while at > textbox.scroll {
+                        at = textbox.prev_visual(at);
+                        shown += 1;
+                    }
+                    prop_assert_eq!(at, textbox.scroll);
+                    prop_assert!(shown <= height);
+                }
+            }
+
+            #[test]
+            fn vim_and_emacs_keys_stay_in_bounds(
+                lines in prop::collection::vec(typed(), 1..4),
+                keys in prop::collection::vec(
+                    prop::sample::select("hjklwbe0$xXdcyupPiaoAIv.G \u{301}界👍\x1b".chars().collect::<Vec<_>>()),
+                    0..60,
+                ),
+                ctrl in prop::collection::vec(
+                    prop::sample::select("aefbnpkwdy".chars().collect::<Vec<_>>()),
+                    0..30,
+                ),
+            ) {
+                let mut textbox = TextBox::new(20);
+                textbox.set_editing_mode(EditingMode::Vim);
+                textbox.load(lines.clone());
+                for ch in keys {
+                    let key = match ch {
+                        '\x1b' => KeyCode::Esc,
+                        ch => KeyCode::Char(ch),
+                    };
+                    textbox.handle_input(key, KeyModifiers::NONE);
+                    check_positions(&textbox);
+                }
+
+                textbox.set_editing_mode(EditingMode::Emacs);
+                textbox.load(lines);
+                for ch in ctrl {
+                    let modifiers = match ch {
+                        'f' | 'b' | 'd' | 'y' => KeyModifiers::ALT,
+                        _ => KeyModifiers::CONTROL,
+                    };
+                    textbox.handle_input(KeyCode::Char(ch), modifiers);
+                    check_positions(&textbox);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,118 @@
+use std::{borrow::Cow, fmt};
+
+use ropey::Rope;
+
+// The editor text as a rope, so edits stay O(log n) however long the note
+// gets. Lines are split on '\n' only and there is always at least one line,
+// empty for an empty buffer. Positions are a row and a byte offset into it.
+#[derive(Clone, Default)]
+pub struct Buffer {
+    rope: Rope,
+}
+
+impl Buffer {
+    pub fn len(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rope.len_bytes() == 0
+    }
+
+    // a line without its line break, empty past the last line. Borrowed unless
+    // the line straddles chunks of the rope
+    pub fn line(&self, row: usize) -> Cow<'_, str> {
+        if row >= self.len() {
+            return Cow::Borrowed("");
+        }
+        let line = self.rope.line(row);
+        let len = line.len_chars();
+        match len > 0 && line.char(len - 1) == '\n' {
+            true => line.slice(..len - 1).into(),
+            false => line.into(),
+        }
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        (0..self.len()).map(|row| self.line(row))
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        self.lines().map(Cow::into_owned).collect()
+    }
+
+    fn char_index(&self, (row, byte): (usize, usize)) -> usize {
+        self.rope.byte_to_char(self.rope.line_to_byte(row) + byte)
+    }
+
+    pub fn insert(&mut self, at: (usize, usize), text: &str) {
+        let index = self.char_index(at);
+        self.rope.insert(index, text);
+    }
+
+    // remove `text`, which must be what the buffer holds at `at`
+    pub fn remove(&mut self, at: (usize, usize), text: &str) {
+        let start = self.char_index(at);
+        self.rope.remove(start..start + text.chars().count());
+    }
+
+    // the text from one position up to another, with lines joined by '\n'
+    pub fn slice(&self, from: (usize, usize), to: (usize, usize)) -> String {
+        let from = self.rope.line_to_byte(from.0) + from.1;
+        let to = self.rope.line_to_byte(to.0) + to.1;
+        self.rope.byte_slice(from..to).to_string()
+    }
+}
+
+impl From<Vec<String>> for Buffer {
+    fn from(lines: Vec<String>) -> Self {
+        Buffer {
+            rope: Rope::from_str(&lines.join("\n")),
+        }
+    }
+}
+
+// the whole text, lines joined by '\n'
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.rope.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.lines()).finish()
+    }
+}
+
+impl<S: AsRef<str>> PartialEq<Vec<S>> for Buffer {
+    fn eq(&self, other: &Vec<S>) -> bool {
+        self.len() == other.len() && self.lines().zip(other).all(|(a, b)| a == b.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_and_edits() {
+        let mut buffer = Buffer::default();
+        assert_eq!(buffer, vec![""]);
+        assert!(buffer.is_empty());
+
+        buffer.insert((0, 0), "one\ntwo\r");
+        assert_eq!(buffer, vec!["one", "two\r"]);
+        assert_eq!(buffer.line(1), "two\r");
+        assert_eq!(buffer.line(5), "");
+
+        buffer.insert((1, 1), "é\n");
+        assert_eq!(buffer, vec!["one", "té", "wo\r"]);
+        assert_eq!(buffer.slice((0, 2), (1, 3)), "e\nté");
+        assert_eq!(buffer.to_string(), "one\nté\nwo\r");
+
+        buffer.remove((0, 2), "e\nté\n");
+        assert_eq!(buffer, vec!["onwo\r"]);
+        assert_eq!(buffer.to_lines(), vec!["onwo\r".to_string()]);
+    }
+}
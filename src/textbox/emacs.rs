@@ -0,0 +1,289 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use super::{EditKind, Pos, TextBox};
+use crate::keybindings::Chord;
+
+// how many kills are remembered for C-y and M-y
+const KILL_RING_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    LineStart,
+    LineEnd,
+    Forward,
+    Backward,
+    NextLine,
+    PrevLine,
+    ForwardWord,
+    BackwardWord,
+    KillLine,
+    KillWord,
+    BackwardKillWord,
+    DeleteForward,
+    Yank,
+    YankPop,
+}
+
+const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+const ALT: KeyModifiers = KeyModifiers::ALT;
+
+// the Emacs layer, keys not bound here fall through to the default editor keys
+const BINDINGS: &[(KeyModifiers, char, Command)] = &[
+    (CTRL, 'a', Command::LineStart),
+    (CTRL, 'e', Command::LineEnd),
+    (CTRL, 'f', Command::Forward),
+    (CTRL, 'b', Command::Backward),
+    (CTRL, 'n', Command::NextLine),
+    (CTRL, 'p', Command::PrevLine),
+    (ALT, 'f', Command::ForwardWord),
+    (ALT, 'b', Command::BackwardWord),
+    (CTRL, 'k', Command::KillLine),
+    (ALT, 'd', Command::KillWord),
+    (CTRL, 'w', Command::BackwardKillWord),
+    (CTRL, 'd', Command::DeleteForward),
+    (CTRL, 'y', Command::Yank),
+    (ALT, 'y', Command::YankPop),
+];
+
+fn lookup(key: KeyCode, modifiers: KeyModifiers) -> Option<Command> {
+    let KeyCode::Char(ch) = key else {
+        return None;
+    };
+    BINDINGS
+        .iter()
+        .find(|&&(mods, bound, _)| mods == modifiers && bound == ch.to_ascii_lowercase())
+        .map(|&(_, _, command)| command)
+}
+
+pub fn is_bound(key: KeyCode, modifiers: KeyModifiers) -> bool {
+    lookup(key, modifiers).is_some()
+}
+
+// commands shown in the nav bar with their keys from the bindings above
+const HINTS: &[(&str, &[Command])] = &[
+    (
+        "kill",
+        &[
+            Command::KillLine,
+            Command::KillWord,
+            Command::BackwardKillWord,
+        ],
+    ),
+    ("yank", &[Command::Yank, Command::YankPop]),
+];
+
+// e.g. `(^K/M-d/^W) kill (^Y/M-y) yank`
+pub fn hints() -> String {
+    HINTS
+        .iter()
+        .map(|(label, commands)| {
+            let keys: Vec<String> = BINDINGS
+                .iter()
+                .filter(|(_, _, command)| commands.contains(command))
+                .map(|&(mods, ch, _)| Chord::new(KeyCode::Char(ch), mods).to_string())
+                .collect();
+            format!("({}) {}", keys.join("/"), label)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Emacs {
+    // newest kill last
+    kill_ring: Vec<String>,
+    // the last command was a kill, the next one adds to the same entry
+    killing: bool,
+    // where the last yank was inserted and which kill it was, for M-y
+    yanked: Option<(Pos, Pos, usize)>,
+}
+
+impl Emacs {
+    pub fn handle_input(&mut self, tb: &mut TextBox, key: KeyCode, modifiers: KeyModifiers) {
+        let killing = std::mem::take(&mut self.killing);
+        let yanked = self.yanked.take();
+        let Some(command) = lookup(key, modifiers) else {
+            tb.handle_default(key, modifiers);
+            return;
+        };
+
+        tb.history.seal();
+        tb.anchor = None;
+        let cursor = (tb.cursor.row, tb.cursor.col);
+        let (row, col) = cursor;
+        match command {
+            Command::LineStart => tb.set_cursor((row, 0)),
+            Command::LineEnd => tb.set_cursor((row, tb.line_len(row))),
+            Command::Forward => tb.handle_default(KeyCode::Right, KeyModifiers::NONE),
+            Command::Backward => tb.handle_default(KeyCode::Left, KeyModifiers::NONE),
+            Command::NextLine => tb.handle_default(KeyCode::Down, KeyModifiers::NONE),
+            Command::PrevLine => tb.handle_default(KeyCode::Up, KeyModifiers::NONE),
+            Command::ForwardWord => tb.set_cursor(forward_word(tb, cursor)),
+            Command::BackwardWord => tb.set_cursor(backward_word(tb, cursor)),
+            // kill to the end of the line, or the line break when already there
+            Command::KillLine => {
+                let len = tb.line_len(row);
+                let end = match col < len {
+                    true => (row, len),
+                    false => tb.next_pos(cursor).unwrap_or(cursor),
+                };
+                self.kill(tb, cursor, end, killing);
+            }
+            Command::KillWord => self.kill(tb, cursor, forward_word(tb, cursor), killing),
+            Command::BackwardKillWord => self.kill(tb, backward_word(tb, cursor), cursor, killing),
+            Command::DeleteForward => {
+                if let Some(end) = tb.next_pos(cursor) {
+                    tb.delete_range(cursor, end);
+                }
+            }
+            Command::Yank => self.yank(tb, 0),
+            // swap the text just yanked for the next older kill
+            Command::YankPop => {
+                if let Some((start, end, index)) = yanked {
+                    tb.delete_range(start, end);
+                    self.yank(tb, index + 1);
+                }
+            }
+        }
+        tb.update_visible_lines();
+    }
+
+    // an editing command from the app breaks a run of kills or yanks
+    pub fn after_command(&mut self) {
+        self.killing = false;
+        self.yanked = None;
+    }
+
+    // delete the range into the kill ring, consecutive kills build up one entry
+    fn kill(&mut self, tb: &mut TextBox, start: Pos, end: Pos, append: bool) {
+        self.killing = true;
+        if start >= end {
+            return;
+        }
+        let text = tb.text_between(start, end);
+        let backward = start < (tb.cursor.row, tb.cursor.col);
+        tb.delete_range(start, end);
+
+        match self.kill_ring.last_mut() {
+            Some(last) if append && backward => last.insert_str(0, &text),
+            Some(last) if append => last.push_str(&text),
+            _ => {
+                self.kill_ring.push(text);
+                if self.kill_ring.len() > KILL_RING_SIZE {
+                    self.kill_ring.remove(0);
+                }
+            }
+        }
+        tb.set_register(self.kill_ring.last().cloned().unwrap_or_default(), false);
+    }
+
+    // insert the kill `index` places back from the newest
+    fn yank(&mut self, tb: &mut TextBox, index: usize) {
+        if self.kill_ring.is_empty() {
+            return;
+        }
+        let index = index % self.kill_ring.len();
+        let text = self.kill_ring[self.kill_ring.len() - 1 - index].clone();
+        let start = (tb.cursor.row, tb.cursor.col);
+        tb.edit(EditKind::Insert, start.0, start.1, text);
+        self.yanked = Some((start, (tb.cursor.row, tb.cursor.col), index));
+    }
+}
+
+fn is_word(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+// end of the current or next word
+fn forward_word(tb: &TextBox, pos: Pos) -> Pos {
+    let mut p = pos;
+    for in_word in [false, true] {
+        while is_word(tb.char_at(p)) == in_word {
+            match tb.next_pos(p) {
+                Some(next) => p = next,
+                None => return p,
+            }
+        }
+    }
+    p
+}
+
+// start of the current or previous word
+fn backward_word(tb: &TextBox, pos: Pos) -> Pos {
+    let mut p = pos;
+    for in_word in [false, true] {
+        while let Some(prev) = tb.prev_pos(p) {
+            if is_word(tb.char_at(prev)) != in_word {
+                break;
+            }
+            p = prev;
+        }
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EditingMode;
+
+    fn emacs(text: &[&str]) -> TextBox {
+        let mut textbox = TextBox::new(20);
+        textbox.set_editing_mode(EditingMode::Emacs);
+        textbox.load(text.iter().map(|line| line.to_string()).collect());
+        textbox
+    }
+
+    fn press(textbox: &mut TextBox, modifiers: KeyModifiers, keys: &str) {
+        for ch in keys.chars() {
+            textbox.handle_input(KeyCode::Char(ch), modifiers);
+        }
+    }
+
+    #[test]
+    fn test_motions() {
+        let mut textbox = emacs(&["foo bar.baz", "qux"]);
+        press(&mut textbox, CTRL, "e");
+        assert_eq!(textbox.cursor.col, 11);
+        press(&mut textbox, ALT, "b");
+        assert_eq!(textbox.cursor.col, 8);
+        press(&mut textbox, CTRL, "a");
+        press(&mut textbox, ALT, "ff");
+        assert_eq!(textbox.cursor.col, 7);
+        press(&mut textbox, CTRL, "nf");
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (1, 3));
+    }
+
+    #[test]
+    fn test_kill_ring_and_yank() {
+        let mut textbox = emacs(&["one two", "three"]);
+        press(&mut textbox, CTRL, "kk");
+        assert_eq!(textbox.text, vec!["three".to_string()]);
+        assert_eq!(textbox.register, "one two\n");
+
+        press(&mut textbox, CTRL, "a");
+        press(&mut textbox, ALT, "d");
+        assert_eq!(textbox.text, vec![String::new()]);
+        press(&mut textbox, CTRL, "y");
+        assert_eq!(textbox.text, vec!["three".to_string()]);
+        press(&mut textbox, ALT, "y");
+        assert_eq!(textbox.text, vec!["one two".to_string(), String::new()]);
+
+        press(&mut textbox, CTRL, "w");
+        assert_eq!(textbox.text, vec!["one ".to_string()]);
+        assert_eq!(textbox.register, "two\n");
+    }
+
+    #[test]
+    fn test_delete_forward_and_fallthrough() {
+        let mut textbox = emacs(&["ab", "c"]);
+        press(&mut textbox, CTRL, "d");
+        press(&mut textbox, KeyModifiers::NONE, "x");
+        assert_eq!(textbox.text, vec!["xb".to_string(), "c".into()]);
+        press(&mut textbox, CTRL, "ed");
+        assert_eq!(textbox.text, vec!["xbc".to_string()]);
+        assert!(is_bound(KeyCode::Char('p'), CTRL));
+        assert!(!is_bound(KeyCode::Char('s'), CTRL));
+        assert_eq!(hints(), "(^K/M-d/^W) kill (^Y/M-y) yank");
+    }
+}
@@ -0,0 +1,203 @@
+use regex::{Captures, Regex};
+
+use super::{EditKind, Pos, TextBox};
+use crate::{graphemes, search};
+
+// Finding and replacing in the open note, driven by the app's find bar.
+// Matches never span lines and empty matches are skipped, like the search
+// over all notes.
+
+impl TextBox {
+    // highlight the matches of a query, None when the find bar closes
+    pub fn set_highlight(&mut self, regex: Option<Regex>) {
+        self.highlight = regex;
+    }
+
+    // the columns matched on a row, for drawing it
+    pub fn highlighted(&self, line: &str) -> Vec<(usize, usize)> {
+        self.highlight
+            .as_ref()
+            .map_or_else(Vec::new, |regex| search::find_in(regex, line))
+    }
+
+    // every match as its start and end, in text order
+    pub fn find_all(&self, regex: &Regex) -> Vec<(Pos, Pos)> {
+        self.text
+            .lines()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                search::find_in(regex, &line)
+                    .into_iter()
+                    .map(move |(start, end)| ((row, start), (row, end)))
+            })
+            .collect()
+    }
+
+    // move to the first match starting at or after `from`, wrapping around
+    // to the top. False when nothing matches
+    pub fn find_forward(&mut self, regex: &Regex, (row, col): Pos) -> bool {
+        let rows = self.text.len();
+        for i in 0..=rows {
+            let at = (row + i) % rows;
+            let mut found = search::find_in(regex, &self.text.line(at)).into_iter();
+            let start = match i {
+                0 => found.find(|&(start, _)| start >= col),
+                _ => found.next(),
+            };
+            if let Some((start, _)) = start {
+                self.move_cursor_to(at, start);
+                return true;
+            }
+        }
+        false
+    }
+
+    // move to the last match starting before `from`, wrapping around to the
+    // bottom. False when nothing matches
+    pub fn find_backward(&mut self, regex: &Regex, (row, col): Pos) -> bool {
+        let rows = self.text.len();
+        for i in 0..=rows {
+            let at = (row + rows - i % rows) % rows;
+            let mut found = search::find_in(regex, &self.text.line(at))
+                .into_iter()
+                .rev();
+            let start = match i {
+                0 => found.find(|&(start, _)| start < col),
+                _ => found.next(),
+            };
+            if let Some((start, _)) = start {
+                self.move_cursor_to(at, start);
+                return true;
+            }
+        }
+        false
+    }
+
+    // replace the match starting at the cursor as one undo step, then move to
+    // the next match. With `expand` the replacement can use capture groups,
+    // `$1` or `${name}`. False when no match starts at the cursor
+    pub fn replace_next(&mut self, regex: &Regex, replacement: &str, expand: bool) -> bool {
+        let row = self.cursor.row;
+        let line = self.text.line(row).into_owned();
+        let byte = graphemes::byte_index(&line, self.cursor.col);
+        let Some(caps) = regex.captures_at(&line, byte) else {
+            return false;
+        };
+        let found = caps.get(0).expect("group 0 is the whole match");
+        if found.start() != byte || found.is_empty() {
+            return false;
+        }
+
+        let with = replace_with(&caps, replacement, expand);
+        self.history.seal();
+        self.replace_range(row, found.range(), with);
+        self.history.seal();
+        let end = (self.cursor.row, self.cursor.col);
+        self.find_forward(regex, end);
+        true
+    }
+
+    // replace every match as a single undo step, returning how many there were
+    pub fn replace_all(&mut self, regex: &Regex, replacement: &str, expand: bool) -> usize {
+        let mut count = 0;
+        self.history.begin_group();
+        // from the end, so the matches left to replace don't move
+        for row in (0..self.text.len()).rev() {
+            let line = self.text.line(row).into_owned();
+            let found: Vec<_> = regex
+                .captures_iter(&line)
+                .filter_map(|caps| {
+                    let found = caps.get(0).filter(|found| !found.is_empty())?;
+                    Some((found.range(), replace_with(&caps, replacement, expand)))
+                })
+                .collect();
+            for (range, with) in found.into_iter().rev() {
+                self.replace_range(row, range, with);
+                count += 1;
+            }
+        }
+        self.history.end_group();
+        count
+    }
+
+    // swap the bytes of a row for other text, joined into one undo step
+    fn replace_range(&mut self, row: usize, range: std::ops::Range<usize>, with: String) {
+        let removed = self.text.line(row)[range.clone()].to_string();
+        self.edit_at_byte(EditKind::Delete, row, range.start, removed);
+        if !with.is_empty() {
+            self.history.join = true;
+            self.edit_at_byte(EditKind::Insert, row, range.start, with);
+        }
+        self.update_visible_lines();
+    }
+}
+
+fn replace_with(caps: &Captures, replacement: &str, expand: bool) -> String {
+    let mut with = String::new();
+    match expand {
+        true => caps.expand(replacement, &mut with),
+        false => with.push_str(replacement),
+    }
+    with
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::SearchOptions;
+
+    fn regex(query: &str, options: SearchOptions) -> Regex {
+        options.compile(query).unwrap()
+    }
+
+    #[test]
+    fn test_find_wraps_around() {
+        let mut textbox = TextBox::from(vec!["a cat".into(), "dog".into(), "cat cat".into()]);
+        let cat = regex("cat", SearchOptions::default());
+        assert_eq!(textbox.find_all(&cat).len(), 3);
+
+        assert!(textbox.find_forward(&cat, (0, 3)));
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (2, 0));
+        assert!(textbox.find_forward(&cat, (2, 1)));
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (2, 4));
+        assert!(textbox.find_forward(&cat, (2, 5)));
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 2));
+
+        assert!(textbox.find_backward(&cat, (0, 2)));
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (2, 4));
+        assert!(!textbox.find_forward(&regex("cow", SearchOptions::default()), (0, 0)));
+    }
+
+    #[test]
+    fn test_replace_with_groups_and_undo() {
+        let text = vec!["Key=1 key=2".into(), "KEY=3".into()];
+        let mut textbox = TextBox::from(text.clone());
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let pair = regex(r"(\w+)=(\d)", options);
+
+        // one at a time, moving on to the next match
+        textbox.move_cursor_to(0, 5);
+        assert!(!textbox.replace_next(&pair, "$2:$1", true));
+        textbox.find_forward(&pair, (0, 0));
+        assert!(textbox.replace_next(&pair, "$2:$1", true));
+        assert_eq!(textbox.text, vec!["1:Key key=2", "KEY=3"]);
+        assert_eq!((textbox.cursor.row, textbox.cursor.col), (0, 6));
+        textbox.undo();
+        assert_eq!(textbox.text, text);
+
+        assert_eq!(textbox.replace_all(&pair, "${2}", true), 3);
+        assert_eq!(textbox.text, vec!["1 2", "3"]);
+        textbox.undo();
+        assert_eq!(textbox.text, text);
+
+        let case = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(textbox.replace_all(&regex("key", case), "$0", false), 1);
+        assert_eq!(textbox.text, vec!["Key=1 $0=2", "KEY=3"]);
+    }
+}
@@ -0,0 +1,61 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::graphemes;
+
+// Split a line into screen rows of at most `width` cells, as grapheme column
+// ranges. Rows break after a blank when one fits, otherwise mid-word. A
+// grapheme wider than the row still gets a row of its own, and an empty line
+// is one empty row.
+pub fn wrap_line(line: &str, width: usize) -> Vec<(usize, usize)> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    // column right after the last blank on the current row
+    let mut break_at = None;
+
+    for (col, grapheme) in line.graphemes(true).enumerate() {
+        let cells = grapheme.width();
+        if used + cells > width && col > start {
+            let end = break_at.unwrap_or(col);
+            rows.push((start, end));
+            used = graphemes::width_between(line, end, col);
+            start = end;
+            break_at = None;
+        }
+        used += cells;
+        if graphemes::is_blank(grapheme) {
+            break_at = Some(col + 1);
+        }
+    }
+    rows.push((start, graphemes::len(line)));
+    rows
+}
+
+// which of the rows holds a column, a column on a break belongs to the row it
+// starts
+pub fn row_of(rows: &[(usize, usize)], col: usize) -> usize {
+    rows.iter()
+        .rposition(|&(start, _)| start <= col)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_at_blanks_and_mid_word() {
+        assert_eq!(wrap_line("", 5), vec![(0, 0)]);
+        assert_eq!(wrap_line("one two three", 8), vec![(0, 8), (8, 13)]);
+        assert_eq!(wrap_line("abcdefgh", 3), vec![(0, 3), (3, 6), (6, 8)]);
+        // wide chars never straddle a row
+        assert_eq!(wrap_line("a界界", 4), vec![(0, 2), (2, 3)]);
+        assert_eq!(wrap_line("界", 1), vec![(0, 1)]);
+
+        let rows = wrap_line("one two three", 8);
+        assert_eq!(row_of(&rows, 7), 0);
+        assert_eq!(row_of(&rows, 8), 1);
+        assert_eq!(row_of(&rows, 13), 1);
+    }
+}
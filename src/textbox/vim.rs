@@ -0,0 +1,939 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::cmp::min;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{EditKind, Pos, TextBox};
+use crate::graphemes;
+
+// counts are capped so a long run of digits can't overflow or hang the editor
+const MAX_COUNT: usize = 10_000;
+// a counted paste stops short of inserting more than this many bytes
+const MAX_PASTE: usize = 1 << 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    Down,
+    Up,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    // only after an operator
+    InnerWord,
+    InnerParagraph,
+    // a doubled operator like `dd`
+    Line,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Command {
+    Move(Motion),
+    Operate(Operator, Motion),
+    DeleteChar,
+    Paste { before: bool },
+    // one of i, a, I, A, o, O
+    Insert(char),
+    Visual,
+    Undo,
+    Repeat,
+}
+
+impl Command {
+    // commands repeated by `.`
+    fn is_change(&self) -> bool {
+        !matches!(
+            self,
+            Command::Move(_)
+                | Command::Operate(Operator::Yank, _)
+                | Command::Visual
+                | Command::Undo
+                | Command::Repeat
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Parse<T> {
+    Incomplete,
+    Invalid,
+    Done(T),
+}
+
+// text covered by a motion, rows only matter for linewise ranges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Range {
+    start: Pos,
+    end: Pos,
+    linewise: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Blank,
+    Word,
+    Punct,
+}
+
+fn class(ch: char) -> Class {
+    if ch.is_whitespace() {
+        Class::Blank
+    } else if ch.is_alphanumeric() || ch == '_' {
+        Class::Word
+    } else {
+        Class::Punct
+    }
+}
+
+// leading count of a command, a lone 0 is the line start motion
+fn take_count(keys: &[char]) -> (Option<usize>, &[char]) {
+    let digits = keys
+        .iter()
+        .enumerate()
+        .take_while(|&(i, ch)| ch.is_ascii_digit() && (i > 0 || *ch != '0'))
+        .count();
+    let count = (digits > 0).then(|| {
+        keys[..digits]
+            .iter()
+            .filter_map(|ch| ch.to_digit(10))
+            .fold(0, |n: usize, digit| {
+                n.saturating_mul(10).saturating_add(digit as usize)
+            })
+            .min(MAX_COUNT)
+    });
+    (count, &keys[digits..])
+}
+
+fn parse_motion(keys: &[char]) -> Parse<Motion> {
+    let motion = match keys {
+        ['h'] => Motion::Left,
+        ['l'] => Motion::Right,
+        ['j'] => Motion::Down,
+        ['k'] => Motion::Up,
+        ['w'] => Motion::WordForward,
+        ['b'] => Motion::WordBackward,
+        ['e'] => Motion::WordEnd,
+        ['0'] => Motion::LineStart,
+        ['$'] => Motion::LineEnd,
+        ['G'] => Motion::LastLine,
+        ['g'] => return Parse::Incomplete,
+        ['g', 'g'] => Motion::FirstLine,
+        _ => return Parse::Invalid,
+    };
+    Parse::Done(motion)
+}
+
+// parse the keys typed in normal mode into a command and its count
+fn parse(keys: &[char]) -> Parse<(Command, Option<usize>)> {
+    let (count, rest) = take_count(keys);
+    let command = match rest {
+        [] => return Parse::Incomplete,
+        ['x'] => Command::DeleteChar,
+        ['p'] => Command::Paste { before: false },
+        ['P'] => Command::Paste { before: true },
+        [ch @ ('i' | 'a' | 'I' | 'A' | 'o' | 'O')] => Command::Insert(*ch),
+        ['v'] => Command::Visual,
+        ['u'] => Command::Undo,
+        ['.'] => Command::Repeat,
+        [op @ ('d' | 'c' | 'y'), tail @ ..] => {
+            let operator = match op {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            let (inner, tail) = take_count(tail);
+            let motion = match tail {
+                [] | ['i'] => return Parse::Incomplete,
+                [ch] if ch == op => Motion::Line,
+                ['i', 'w'] => Motion::InnerWord,
+                ['i', 'p'] => Motion::InnerParagraph,
+                _ => match parse_motion(tail) {
+                    Parse::Done(motion) => motion,
+                    Parse::Incomplete => return Parse::Incomplete,
+                    Parse::Invalid => return Parse::Invalid,
+                },
+            };
+            let count = match (count, inner) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(1).saturating_mul(b.unwrap_or(1)).min(MAX_COUNT)),
+            };
+            return Parse::Done((Command::Operate(operator, motion), count));
+        }
+        _ => match parse_motion(rest) {
+            Parse::Done(motion) => Command::Move(motion),
+            Parse::Incomplete => return Parse::Incomplete,
+            Parse::Invalid => return Parse::Invalid,
+        },
+    };
+    Parse::Done((command, count))
+}
+
+// normal mode understands a few special keys as their letter motions
+fn key_char(key: KeyCode) -> Option<char> {
+    match key {
+        KeyCode::Char(ch) => Some(ch),
+        KeyCode::Left | KeyCode::Backspace => Some('h'),
+        KeyCode::Right => Some('l'),
+        KeyCode::Down | KeyCode::Enter => Some('j'),
+        KeyCode::Up => Some('k'),
+        KeyCode::Home => Some('0'),
+        KeyCode::End => Some('$'),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    // keys of a command that isn't complete yet, e.g. `d2`
+    pending: Vec<char>,
+    // keys of the last change, replayed by `.`
+    last_change: Vec<(KeyCode, KeyModifiers)>,
+    // keys of the change being typed until it is complete
+    recording: Vec<(KeyCode, KeyModifiers)>,
+}
+
+impl Vim {
+    pub fn handle_input(&mut self, tb: &mut TextBox, key: KeyCode, modifiers: KeyModifiers) {
+        // C-c leaves insert and visual mode like Esc
+        let (key, modifiers) = match self.claims_key(key, modifiers) {
+            true => (KeyCode::Esc, KeyModifiers::NONE),
+            false => (key, modifiers),
+        };
+        match self.mode {
+            Mode::Insert => self.insert_key(tb, key, modifiers),
+            Mode::Normal | Mode::Visual => self.normal_key(tb, key, modifiers),
+        }
+        tb.update_visible_lines();
+    }
+
+    pub fn claims_key(&self, key: KeyCode, modifiers: KeyModifiers) -> bool {
+        let ctrl_c = matches!(key, KeyCode::Char('c') | KeyCode::Char('C'))
+            && modifiers.contains(KeyModifiers::CONTROL);
+        ctrl_c && self.mode != Mode::Normal
+    }
+
+    pub fn wants_esc(&self) -> bool {
+        self.mode != Mode::Normal || !self.pending.is_empty()
+    }
+
+    pub fn indicator(&self) -> String {
+        let mode = match self.mode {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        };
+        let pending: String = self.pending.iter().collect();
+        format!("-- {} -- {}", mode, pending).trim_end().to_string()
+    }
+
+    // back to normal mode when another note is opened
+    pub fn reset(&mut self) {
+        self.mode = Mode::Normal;
+        self.pending.clear();
+        self.recording.clear();
+    }
+
+    // an editing command from the app ends visual mode like any other change
+    pub fn after_command(&mut self, tb: &mut TextBox) {
+        if self.mode != Mode::Insert {
+            self.leave_visual(tb);
+            clamp_cursor(tb);
+        }
+    }
+
+    // pasted text replaces the visual selection, including the char under
+    // the cursor, then leaves visual mode and the cursor on a char like other
+    // changes
+    pub fn paste_text(&mut self, tb: &mut TextBox, text: &str) {
+        if self.mode == Mode::Visual {
+            let range = visual_range(tb);
+            tb.anchor = Some(range.start);
+            tb.set_cursor(range.end);
+        }
+        tb.insert_str(text);
+        self.after_command(tb);
+    }
+
+    fn insert_key(&mut self, tb: &mut TextBox, key: KeyCode, modifiers: KeyModifiers) {
+        // a change from visual mode isn't recorded, so neither is its insert
+        let recording = !self.recording.is_empty();
+        if recording {
+            self.recording.push((key, modifiers));
+        }
+        if key != KeyCode::Esc {
+            tb.handle_default(key, modifiers);
+            return;
+        }
+
+        self.mode = Mode::Normal;
+        tb.history.end_group();
+        if recording {
+            self.last_change = std::mem::take(&mut self.recording);
+        }
+        let (row, col) = (tb.cursor.row, tb.cursor.col);
+        tb.set_cursor((row, col.saturating_sub(1)));
+    }
+
+    fn normal_key(&mut self, tb: &mut TextBox, key: KeyCode, modifiers: KeyModifiers) {
+        if key == KeyCode::Esc {
+            self.pending.clear();
+            self.recording.clear();
+            self.leave_visual(tb);
+            return;
+        }
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            match key {
+                KeyCode::Char('r') | KeyCode::Char('R') => tb.redo(),
+                _ => tb.handle_default(key, modifiers),
+            }
+            self.leave_visual(tb);
+            clamp_cursor(tb);
+            return;
+        }
+        let Some(ch) = key_char(key) else {
+            return;
+        };
+
+        if self.mode == Mode::Visual && self.pending.is_empty() {
+            let operator = match ch {
+                'd' | 'x' => Some(Operator::Delete),
+                'c' => Some(Operator::Change),
+                'y' => Some(Operator::Yank),
+                'v' => {
+                    self.leave_visual(tb);
+                    return;
+                }
+                _ => None,
+            };
+            if let Some(operator) = operator {
+                let range = visual_range(tb);
+                self.leave_visual(tb);
+                tb.history.seal();
+                self.operate(tb, operator, range);
+                if self.mode == Mode::Normal {
+                    clamp_cursor(tb);
+                }
+                return;
+            }
+        }
+
+        self.pending.push(ch);
+        self.recording.push((key, modifiers));
+        match parse(&self.pending) {
+            Parse::Incomplete => {}
+            Parse::Invalid => {
+                self.pending.clear();
+                self.recording.clear();
+            }
+            Parse::Done((command, count)) => {
+                self.pending.clear();
+                self.run(tb, command, count);
+            }
+        }
+    }
+
+    fn run(&mut self, tb: &mut TextBox, command: Command, count: Option<usize>) {
+        let n = count.unwrap_or(1).max(1);
+        let cursor = (tb.cursor.row, tb.cursor.col);
+        let recording = self.mode == Mode::Normal && command.is_change();
+        if !recording {
+            self.recording.clear();
+        }
+        tb.history.seal();
+
+        match command {
+            Command::Move(motion) => {
+                match motion {
+                    // vertical motions go by screen row like the arrows, and
+                    // keep the column they started from
+                    Motion::Down | Motion::Up => {
+                        for _ in 0..n {
+                            let at = (tb.cursor.row, tb.cursor.col);
+                            match motion {
+                                Motion::Down => tb.move_cursor_down(),
+                                _ => tb.move_cursor_up(),
+                            }
+                            if (tb.cursor.row, tb.cursor.col) == at {
+                                break;
+                            }
+                        }
+                    }
+                    _ => tb.set_cursor(motion_target(tb, motion, count)),
+                }
+            }
+            Command::Operate(operator, motion) => {
+                // like Vim, `cw` on a word changes to its end, not the next word
+                let motion = match (operator, motion) {
+                    (Operator::Change, Motion::WordForward)
+                        if class(tb.char_at(cursor)) != Class::Blank =>
+                    {
+                        Motion::WordEnd
+                    }
+                    _ => motion,
+                };
+                if let Some(range) = motion_range(tb, motion, count) {
+                    self.operate(tb, operator, range);
+                }
+            }
+            Command::DeleteChar => {
+                let end = min(cursor.1.saturating_add(n), tb.line_len(cursor.0));
+                if end > cursor.1 {
+                    let range = Range {
+                        start: cursor,
+                        end: (cursor.0, end),
+                        linewise: false,
+                    };
+                    self.operate(tb, Operator::Delete, range);
+                }
+            }
+            Command::Paste { before } => self.paste(tb, before, n),
+            Command::Insert(ch) => self.start_insert(tb, ch),
+            Command::Visual => {
+                self.mode = Mode::Visual;
+                tb.anchor = Some(cursor);
+            }
+            Command::Undo => {
+                for _ in 0..n {
+                    tb.undo();
+                }
+            }
+            Command::Repeat => {
+                let keys = self.last_change.clone();
+                for _ in 0..n {
+                    for &(key, modifiers) in &keys {
+                        self.handle_input(tb, key, modifiers);
+                    }
+                }
+            }
+        }
+
+        if recording && self.mode != Mode::Insert {
+            self.last_change = std::mem::take(&mut self.recording);
+        }
+        if self.mode != Mode::Insert {
+            clamp_cursor(tb);
+        }
+    }
+
+    fn leave_visual(&mut self, tb: &mut TextBox) {
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+        }
+        tb.anchor = None;
+    }
+
+    fn operate(&mut self, tb: &mut TextBox, operator: Operator, range: Range) {
+        let Range {
+            start,
+            end,
+            linewise,
+        } = range;
+        let text = if linewise {
+            tb.text_between((start.0, 0), (end.0, tb.line_len(end.0)))
+        } else {
+            tb.text_between(start, end)
+        };
+        tb.set_register(text, linewise);
+
+        match operator {
+            Operator::Yank if linewise => tb.set_cursor((start.0, 0)),
+            Operator::Yank => tb.set_cursor(start),
+            Operator::Delete if linewise => delete_lines(tb, start.0, end.0),
+            Operator::Delete => tb.delete_range(start, end),
+            Operator::Change => {
+                tb.history.begin_group();
+                if linewise {
+                    tb.delete_range((start.0, 0), (end.0, tb.line_len(end.0)));
+                    tb.set_cursor((start.0, 0));
+                } else {
+                    tb.delete_range(start, end);
+                }
+                self.mode = Mode::Insert;
+            }
+        }
+    }
+
+    fn paste(&mut self, tb: &mut TextBox, before: bool, n: usize) {
+        if tb.register.is_empty() {
+            return;
+        }
+        let (row, col) = (tb.cursor.row, tb.cursor.col);
+
+        let n = n.min(MAX_PASTE / tb.register.len()).max(1);
+        if tb.linewise {
+            let block = vec![tb.register.clone(); n].join("\n");
+            if before {
+                tb.edit(EditKind::Insert, row, 0, format!("{}\n", block));
+                tb.set_cursor((row, 0));
+            } else {
+                let len = tb.line_len(row);
+                tb.edit(EditKind::Insert, row, len, format!("\n{}", block));
+                tb.set_cursor((row + 1, 0));
+            }
+        } else {
+            let text = tb.register.repeat(n);
+            let col = if before {
+                col
+            } else {
+                min(col + 1, tb.line_len(row))
+            };
+            tb.edit(EditKind::Insert, row, col, text);
+            // rest on the last pasted char
+            let (row, col) = (tb.cursor.row, tb.cursor.col);
+            tb.set_cursor((row, col.saturating_sub(1)));
+        }
+    }
+
+    fn start_insert(&mut self, tb: &mut TextBox, ch: char) {
+        tb.history.begin_group();
+        let (row, col) = (tb.cursor.row, tb.cursor.col);
+        let len = tb.line_len(row);
+        match ch {
+            'a' => tb.set_cursor((row, min(col + 1, len))),
+            'I' => {
+                let indent = tb
+                    .text
+                    .line(row)
+                    .graphemes(true)
+                    .take_while(|grapheme| graphemes::is_blank(grapheme))
+                    .count();
+                tb.set_cursor((row, indent))
+            }
+            'A' => tb.set_cursor((row, len)),
+            'o' => tb.edit(EditKind::Insert, row, len, "\n".into()),
+            'O' => {
+                tb.edit(EditKind::Insert, row, 0, "\n".into());
+                tb.set_cursor((row, 0));
+            }
+            _ => {}
+        }
+        self.mode = Mode::Insert;
+    }
+}
+
+fn is_empty_line(tb: &TextBox, (row, col): Pos) -> bool {
+    col == 0 && tb.line_len(row) == 0
+}
+
+// start of the next word, an empty line counts as a word
+fn word_forward(tb: &TextBox, pos: Pos) -> Pos {
+    let start = class(tb.char_at(pos));
+    let mut p = pos;
+    if start != Class::Blank {
+        while let Some(next) = tb.next_pos(p) {
+            p = next;
+            if class(tb.char_at(p)) != start {
+                break;
+            }
+        }
+    }
+    while class(tb.char_at(p)) == Class::Blank {
+        if p != pos && is_empty_line(tb, p) {
+            break;
+        }
+        match tb.next_pos(p) {
+            Some(next) => p = next,
+            None => break,
+        }
+    }
+    p
+}
+
+// start of the current or previous word
+fn word_backward(tb: &TextBox, pos: Pos) -> Pos {
+    let Some(mut p) = tb.prev_pos(pos) else {
+        return pos;
+    };
+    while class(tb.char_at(p)) == Class::Blank {
+        if is_empty_line(tb, p) {
+            return p;
+        }
+        match tb.prev_pos(p) {
+            Some(prev) => p = prev,
+            None => return p,
+        }
+    }
+    let word = class(tb.char_at(p));
+    while let Some(prev) = tb.prev_pos(p) {
+        if class(tb.char_at(prev)) != word {
+            break;
+        }
+        p = prev;
+    }
+    p
+}
+
+// last char of the current or next word
+fn word_end(tb: &TextBox, pos: Pos) -> Pos {
+    let Some(mut p) = tb.next_pos(pos) else {
+        return pos;
+    };
+    while class(tb.char_at(p)) == Class::Blank {
+        match tb.next_pos(p) {
+            Some(next) => p = next,
+            None => return p,
+        }
+    }
+    let word = class(tb.char_at(p));
+    while let Some(next) = tb.next_pos(p) {
+        if class(tb.char_at(next)) != word {
+            break;
+        }
+        p = next;
+    }
+    p
+}
+
+fn last_row(tb: &TextBox) -> usize {
+    tb.text.len().saturating_sub(1)
+}
+
+fn motion_target(tb: &TextBox, motion: Motion, count: Option<usize>) -> Pos {
+    let n = count.unwrap_or(1).max(1);
+    let (row, col) = (tb.cursor.row, tb.cursor.col);
+    // word motions stop early once they can't move any further
+    let repeat = |step: fn(&TextBox, Pos) -> Pos| {
+        let mut p = (row, col);
+        for _ in 0..n {
+            let next = step(tb, p);
+            if next == p {
+                break;
+            }
+            p = next;
+        }
+        p
+    };
+
+    match motion {
+        Motion::Left => (row, col.saturating_sub(n)),
+        Motion::Right => (row, min(col.saturating_add(n), tb.line_len(row))),
+        Motion::Down => (min(row.saturating_add(n), last_row(tb)), col),
+        Motion::Up => (row.saturating_sub(n), col),
+        Motion::WordForward => repeat(word_forward),
+        Motion::WordBackward => repeat(word_backward),
+        Motion::WordEnd => repeat(word_end),
+        Motion::LineStart => (row, 0),
+        Motion::LineEnd => {
+            let row = min(row.saturating_add(n - 1), last_row(tb));
+            (row, tb.line_len(row).saturating_sub(1))
+        }
+        Motion::FirstLine => (min(count.unwrap_or(1) - 1, last_row(tb)), 0),
+        Motion::LastLine => (min(count.unwrap_or(usize::MAX) - 1, last_row(tb)), 0),
+        Motion::InnerWord | Motion::InnerParagraph | Motion::Line => (row, col),
+    }
+}
+
+// the text an operator acts on, None if it would be empty
+fn motion_range(tb: &TextBox, motion: Motion, count: Option<usize>) -> Option<Range> {
+    let n = count.unwrap_or(1).max(1);
+    let cursor = (tb.cursor.row, tb.cursor.col);
+    let lines = |a: usize, b: usize| Range {
+        start: (min(a, b), 0),
+        end: (a.max(b), 0),
+        linewise: true,
+    };
+
+    let range = match motion {
+        Motion::Line => lines(cursor.0, min(cursor.0.saturating_add(n - 1), last_row(tb))),
+        Motion::Down | Motion::Up | Motion::FirstLine | Motion::LastLine => {
+            lines(cursor.0, motion_target(tb, motion, count).0)
+        }
+        Motion::InnerWord => inner_word(tb)?,
+        Motion::InnerParagraph => inner_paragraph(tb),
+        // inclusive motions also cover the char they land on
+        Motion::WordEnd | Motion::LineEnd => {
+            let (row, col) = motion_target(tb, motion, count);
+            Range {
+                start: cursor,
+                end: (row, min(col + 1, tb.line_len(row))),
+                linewise: false,
+            }
+        }
+        Motion::WordForward => {
+            let mut end = motion_target(tb, motion, count);
+            // `dw` on the last word of a line leaves the line break alone
+            if end.0 > cursor.0 && end.1 == 0 {
+                end = (end.0 - 1, tb.line_len(end.0 - 1));
+            }
+            Range {
+                start: cursor,
+                end,
+                linewise: false,
+            }
+        }
+        _ => {
+            let target = motion_target(tb, motion, count);
+            Range {
+                start: min(cursor, target),
+                end: cursor.max(target),
+                linewise: false,
+            }
+        }
+    };
+
+    let empty = !range.linewise && range.start >= range.end;
+    (!empty).then_some(range)
+}
+
+// the run of word, punctuation or blank chars under the cursor
+fn inner_word(tb: &TextBox) -> Option<Range> {
+    let (row, col) = (tb.cursor.row, tb.cursor.col);
+    let chars: Vec<char> = tb
+        .text
+        .line(row)
+        .graphemes(true)
+        .filter_map(|grapheme| grapheme.chars().next())
+        .collect();
+    let word = class(*chars.get(col)?);
+    let same = |i: &usize| class(chars[*i]) == word;
+
+    let start = (0..col).rev().take_while(same).last().unwrap_or(col);
+    let end = (col..chars.len()).take_while(same).last().unwrap_or(col) + 1;
+    Some(Range {
+        start: (row, start),
+        end: (row, end),
+        linewise: false,
+    })
+}
+
+// the block of non-empty, or empty, lines around the cursor
+fn inner_paragraph(tb: &TextBox) -> Range {
+    let row = tb.cursor.row;
+    let blank = |r: usize| tb.text.line(r).trim().is_empty();
+    let kind = blank(row);
+
+    let start = (0..row).rev().take_while(|&r| blank(r) == kind).last();
+    let end = (row + 1..tb.text.len())
+        .take_while(|&r| blank(r) == kind)
+        .last();
+    Range {
+        start: (start.unwrap_or(row), 0),
+        end: (end.unwrap_or(row), 0),
+        linewise: true,
+    }
+}
+
+// the visual selection, which includes the char under the cursor
+fn visual_range(tb: &TextBox) -> Range {
+    let cursor = (tb.cursor.row, tb.cursor.col);
+    let anchor = tb.anchor.unwrap_or(cursor);
+    let (start, (row, col)) = (min(anchor, cursor), anchor.max(cursor));
+    Range {
+        start,
+        end: (row, min(col + 1, tb.line_len(row))),
+        linewise: false,
+    }
+}
+
+// remove whole lines including their line break
+fn delete_lines(tb: &mut TextBox, first: usize, last: usize) {
+    let (start, end) = if last < last_row(tb) {
+        ((first, 0), (last + 1, 0))
+    } else if first > 0 {
+        (
+            (first - 1, tb.line_len(first - 1)),
+            (last, tb.line_len(last)),
+        )
+    } else {
+        ((0, 0), (last, tb.line_len(last)))
+    };
+    tb.delete_range(start, end);
+    tb.set_cursor((min(first, last_row(tb)), 0));
+}
+
+// normal mode keeps the cursor on a char, not past the end of the line
+fn clamp_cursor(tb: &mut TextBox) {
+    let row = min(tb.cursor.row, last_row(tb));
+    let len = tb.line_len(row);
+    tb.cursor.row = row;
+    tb.cursor.col = min(tb.cursor.col, len.saturating_sub(1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::EditingMode, textbox::EditCommand};
+
+    fn vim(text: &[&str]) -> TextBox {
+        let mut textbox = TextBox::new(20);
+        textbox.set_editing_mode(EditingMode::Vim);
+        textbox.load(text.iter().map(|line| line.to_string()).collect());
+        textbox
+    }
+
+    fn keys(textbox: &mut TextBox, input: &str) {
+        for ch in input.chars() {
+            let key = match ch {
+                '\x1b' => KeyCode::Esc,
+                ch => KeyCode::Char(ch),
+            };
+            textbox.handle_input(key, KeyModifiers::NONE);
+        }
+    }
+
+    fn cursor(textbox: &TextBox) -> Pos {
+        (textbox.cursor.row, textbox.cursor.col)
+    }
+
+    #[test]
+    fn test_parse_counts_and_operators() {
+        let keys: Vec<char> = "3d2w".chars().collect();
+        assert_eq!(
+            parse(&keys),
+            Parse::Done((
+                Command::Operate(Operator::Delete, Motion::WordForward),
+                Some(6)
+            ))
+        );
+        assert_eq!(parse(&['d', 'g']), Parse::Incomplete);
+        assert_eq!(
+            parse(&['0']),
+            Parse::Done((Command::Move(Motion::LineStart), None))
+        );
+        assert_eq!(parse(&['d', 'z']), Parse::Invalid);
+    }
+
+    #[test]
+    fn test_huge_counts_are_capped() {
+        let keys_of = |input: &str| input.chars().collect::<Vec<char>>();
+        assert_eq!(
+            parse(&keys_of("99999999999d99999999999w")),
+            Parse::Done((
+                Command::Operate(Operator::Delete, Motion::WordForward),
+                Some(MAX_COUNT)
+            ))
+        );
+
+        let mut textbox = vim(&["one two", "three"]);
+        keys(&mut textbox, "99999999999w");
+        assert_eq!(cursor(&textbox), (1, 4));
+        keys(&mut textbox, "gg99999999999$");
+        assert_eq!(cursor(&textbox), (1, 4));
+        keys(&mut textbox, "gg99999999999d99999999999w");
+        assert_eq!(textbox.text, vec![""]);
+
+        let mut textbox = vim(&["ab"]);
+        keys(&mut textbox, "yl99999999999p");
+        assert_eq!(textbox.line_len(0), MAX_COUNT + 2);
+        keys(&mut textbox, "99999999999dd");
+        assert_eq!(textbox.text, vec![""]);
+    }
+
+    #[test]
+    fn test_motions() {
+        let mut textbox = vim(&["foo bar.baz", "", "qux"]);
+        keys(&mut textbox, "w");
+        assert_eq!(cursor(&textbox), (0, 4));
+        keys(&mut textbox, "2w");
+        assert_eq!(cursor(&textbox), (0, 8));
+        keys(&mut textbox, "w");
+        assert_eq!(cursor(&textbox), (1, 0));
+        keys(&mut textbox, "b");
+        assert_eq!(cursor(&textbox), (0, 8));
+        keys(&mut textbox, "0e");
+        assert_eq!(cursor(&textbox), (0, 2));
+        keys(&mut textbox, "$");
+        assert_eq!(cursor(&textbox), (0, 10));
+        keys(&mut textbox, "G");
+        assert_eq!(cursor(&textbox), (2, 0));
+        keys(&mut textbox, "gg2l");
+        assert_eq!(cursor(&textbox), (0, 2));
+    }
+
+    #[test]
+    fn test_operators_and_text_objects() {
+        let mut textbox = vim(&["one two three", "four", "", "five"]);
+        keys(&mut textbox, "dw");
+        assert_eq!(textbox.text.line(0), "two three");
+        keys(&mut textbox, "wdiw");
+        assert_eq!(textbox.text.line(0), "two ");
+
+        keys(&mut textbox, "yyjp");
+        assert_eq!(textbox.text, vec!["two ", "four", "two ", "", "five"]);
+        assert_eq!(cursor(&textbox), (2, 0));
+
+        keys(&mut textbox, "dip");
+        assert_eq!(textbox.text, vec!["", "five"]);
+        keys(&mut textbox, "u");
+        assert_eq!(textbox.text, vec!["two ", "four", "two ", "", "five"]);
+    }
+
+    #[test]
+    fn test_change_and_dot_repeat() {
+        let mut textbox = vim(&["foo foo foo"]);
+        keys(&mut textbox, "cwbar\x1b");
+        assert_eq!(textbox.text.line(0), "bar foo foo");
+        assert_eq!(textbox.mode_indicator().unwrap(), "-- NORMAL --");
+
+        keys(&mut textbox, "w.w.");
+        assert_eq!(textbox.text.line(0), "bar bar bar");
+
+        // the whole change is a single undo step
+        keys(&mut textbox, "u");
+        assert_eq!(textbox.text.line(0), "bar bar foo");
+
+        keys(&mut textbox, "0x.");
+        assert_eq!(textbox.text.line(0), "r bar foo");
+        keys(&mut textbox, "2.");
+        assert_eq!(textbox.text.line(0), "bar foo");
+    }
+
+    #[test]
+    fn test_register_from_outside_vim_isnt_linewise() {
+        let mut textbox = vim(&["hello"]);
+        keys(&mut textbox, "yylvll");
+        textbox.command(EditCommand::Copy);
+        assert_eq!(textbox.register, "el");
+        keys(&mut textbox, "p");
+        assert_eq!(textbox.text.to_lines(), vec!["hellelo"]);
+    }
+
+    #[test]
+    fn test_visual_mode() {
+        let mut textbox = vim(&["hello world"]);
+        keys(&mut textbox, "lvlly");
+        assert_eq!(textbox.register, "ell");
+        assert_eq!(cursor(&textbox), (0, 1));
+
+        keys(&mut textbox, "ve");
+        assert_eq!(textbox.mode_indicator().unwrap(), "-- VISUAL --");
+        keys(&mut textbox, "d");
+        assert_eq!(textbox.text.line(0), "h world");
+        assert!(!textbox.wants_esc());
+    }
+
+    #[test]
+    fn test_terminal_paste() {
+        let mut textbox = vim(&["ab"]);
+        keys(&mut textbox, "$");
+        textbox.paste_text("xy");
+        assert_eq!(textbox.text.line(0), "axyb");
+        assert_eq!(cursor(&textbox), (0, 3));
+
+        keys(&mut textbox, "0vl");
+        textbox.paste_text("z");
+        assert_eq!(textbox.text.line(0), "zyb");
+        assert_eq!(textbox.mode_indicator().unwrap(), "-- NORMAL --");
+        assert!(textbox.anchor.is_none());
+    }
+}
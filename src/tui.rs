@@ -1,13 +1,16 @@
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture,
+    },
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::{io, panic};
 
 pub type Terminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<std::io::Stderr>>;
 
-use crate::{app::AppState, event::EventHandler, ui};
+use crate::{app::AppState, clipboard::SetClipboard, event::EventHandler, ui};
 
 pub struct Tui {
     terminal: Terminal,
@@ -21,7 +24,13 @@ impl Tui {
 
     pub fn enter(&mut self) -> Result<()> {
         terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste,
+            EnableFocusChange
+        )?;
 
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
@@ -29,7 +38,7 @@ impl Tui {
             panic_hook(panic);
         }));
 
-        self.terminal.hide_cursor()?; 
+        self.terminal.hide_cursor()?;
         self.terminal.clear()?;
         Ok(())
     }
@@ -39,9 +48,21 @@ impl Tui {
         Ok(())
     }
 
+    // hand the text to the terminal's clipboard with OSC 52
+    pub fn set_clipboard(&mut self, text: &str) -> Result<()> {
+        crossterm::execute!(self.terminal.backend_mut(), SetClipboard(text))?;
+        Ok(())
+    }
+
     pub fn reset() -> Result<()> {
         terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste,
+            DisableFocusChange
+        )?;
         Ok(())
     }
 
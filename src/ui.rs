@@ -1,27 +1,87 @@
-use ratatui::layout::{Constraint, Layout};
+use chrono::Local;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::{Alignment, Frame};
 use ratatui::style::{Color, Modifier, Style, Stylize};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::widgets::{
+    Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table,
+    TableState, Wrap,
+};
+use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
 
-use crate::app::{AppState, CurrentView};
-use crate::note::NoteList;
+use crate::app::{AppState, Confirm, CurrentView, FindBar, QuickOpen, Recovery};
+use crate::config::Column;
+use crate::fuzzy::FuzzyMatch;
+use crate::keybindings::{self, Action, Chord, Scope};
+use crate::note::Note;
+use crate::notebook::TreeRow;
 
 pub fn render(app: &mut AppState, frame: &mut Frame) {
     let layout = Layout::default()
-        .direction(ratatui::layout::Direction::Vertical)
+        .direction(Direction::Vertical)
         .constraints([Constraint::Min(1), Constraint::Length(2)])
         .split(frame.size());
 
     match app.current_view {
         CurrentView::Main => {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(20), Constraint::Min(1)])
+                .split(layout[0]);
+
+            let mut tree_idx = ListState::default();
+            tree_idx.select(Some(app.tree_index()));
+            let tree = render_tree(app.tree_rows(), app.sidebar_focus);
+            frame.render_stateful_widget(tree, panes[0], &mut tree_idx);
+
             let mut idx = TableState::default();
-            idx.select(Some(app.current_note));
-            let list = render_notes(&mut app.notes);
-            frame.render_stateful_widget(list, layout[0], &mut idx);
+            idx.select(app.current_index());
+            let columns = &app.config.columns;
+            let widths: Vec<Constraint> = columns.iter().map(|&col| column_width(col)).collect();
+            let visible = app.visible_notes();
+            let notes: Vec<&Note> = visible
+                .iter()
+                .filter_map(|&id| app.notes.find(id))
+                .collect();
+            let list = render_notes(notes, columns, &widths, !app.sidebar_focus);
+            frame.render_stateful_widget(list, panes[1], &mut idx);
         }
         CurrentView::Editing => {
-            frame.render_widget(app.textbox.clone(), layout[0]);
+            let panes = match app.preview {
+                Some(_) => Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(layout[0]),
+                None => Rc::from([layout[0]]),
+            };
+            let area = panes[0];
+            app.textbox.set_viewport(
+                area.width.saturating_sub(2) as usize,
+                area.height.saturating_sub(2) as usize,
+            );
+            frame.render_widget(&app.textbox, area);
+            if let Some(&area) = panes.get(1) {
+                render_preview(app, frame, area);
+            }
+        }
+        CurrentView::Trash => {
+            let mut idx = TableState::default();
+            idx.select(app.trashed_index());
+            let trash = render_trash(app);
+            frame.render_stateful_widget(trash, layout[0], &mut idx);
+        }
+        CurrentView::Search => {
+            let mut idx = TableState::default();
+            idx.select((!app.search_results.is_empty()).then_some(app.search_selected));
+            let results = render_search(app);
+            frame.render_stateful_widget(results, layout[0], &mut idx);
+        }
+        CurrentView::Tasks => {
+            let mut idx = TableState::default();
+            idx.select((!app.tasks.is_empty()).then_some(app.task_selected));
+            let tasks = render_tasks(app);
+            frame.render_stateful_widget(tasks, layout[0], &mut idx);
         }
     }
 
@@ -34,61 +94,629 @@ pub fn render(app: &mut AppState, frame: &mut Frame) {
             .alignment(Alignment::Center),
         frame.size(),
     );
+
+    if let Some(quick_open) = &app.quick_open {
+        let area = centered_rect(60, 50, frame.size());
+        frame.render_widget(Clear, area);
+        render_quick_open(app, quick_open, frame, area);
+    }
+
+    if let Some(confirm) = app.confirm {
+        let area = centered_rect(50, 20, frame.size());
+        frame.render_widget(Clear, area);
+        frame.render_widget(render_confirm(app, confirm), area);
+    }
+
+    if let Some(recovery) = &app.recovery {
+        let area = centered_rect(60, 50, frame.size());
+        frame.render_widget(Clear, area);
+        frame.render_widget(render_recovery(app, recovery), area);
+    }
 }
 
-fn render_notes(note_list: &mut NoteList) -> Table<'_> {
-    let rows: Vec<Row> = note_list
+// the rendered note beside the editor, once the editor has scrolled
+fn render_preview(app: &mut AppState, frame: &mut Frame, area: Rect) {
+    let height = area.height.saturating_sub(2) as usize;
+    app.sync_preview(area.width.saturating_sub(2) as usize, height);
+    let Some(pane) = &app.preview else {
+        return;
+    };
+    let lines: Vec<Line> = pane
+        .preview
+        .lines
         .iter()
-        .enumerate()
-        .map(|(id, note)| {
+        .skip(pane.scroll)
+        .take(height)
+        .cloned()
+        .collect();
+    let preview = Paragraph::new(lines).block(
+        Block::default()
+            .title("Preview")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain),
+    );
+    frame.render_widget(preview, area);
+}
+
+// a rect of the given percentage size in the middle of `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn render_recovery<'a>(app: &AppState, recovery: &'a Recovery) -> Paragraph<'a> {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "The notes database couldn't be loaded.",
+            Style::default().bold(),
+        )),
+        Line::from(""),
+    ];
+
+    if recovery.show_details {
+        lines.push(Line::from(recovery.error.to_string()));
+        lines.push(Line::from(""));
+    }
+
+    // one line per action, leaving out unbound ones
+    let hint = |action: Action| {
+        let keys = action_keys(app, Scope::Recovery, action);
+        (!keys.is_empty()).then(|| format!("({}) {}", keys, action.label()))
+    };
+    match recovery.backups.first() {
+        Some(backup) => lines.extend(
+            hint(Action::RestoreBackup)
+                .map(|hint| Line::from(format!("{} {}", hint, backup.display()))),
+        ),
+        None => lines.push(Line::from("No backups available")),
+    }
+    for action in [Action::StartEmpty, Action::ToggleDetails, Action::Quit] {
+        lines.extend(hint(action).map(Line::from));
+    }
+
+    Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title("Recovery")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain),
+        )
+        .wrap(Wrap { trim: false })
+}
+
+fn column_header(column: Column) -> &'static str {
+    match column {
+        Column::Id => "ID",
+        Column::Title => "Title",
+        Column::CreatedAt => "Created At",
+        Column::UpdatedAt => "Updated At",
+        Column::Edits => "Edits",
+        Column::Words => "Words",
+        Column::Tags => "Tags",
+    }
+}
+
+fn column_width(column: Column) -> Constraint {
+    match column {
+        Column::Id => Constraint::Percentage(10),
+        Column::Title => Constraint::Percentage(30),
+        Column::CreatedAt | Column::UpdatedAt => Constraint::Percentage(20),
+        Column::Edits | Column::Words => Constraint::Percentage(8),
+        Column::Tags => Constraint::Percentage(20),
+    }
+}
+
+fn column_cell(column: Column, note: &Note) -> Cell<'_> {
+    let text = match column {
+        Column::Id => note.id.short(),
+        Column::Title => note.title.clone(),
+        Column::CreatedAt => note.created_at.to_string(),
+        Column::UpdatedAt => note
+            .updated_at
+            .map(|time| time.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        Column::Edits => note.edits.to_string(),
+        Column::Words => note.word_count.to_string(),
+        Column::Tags => note
+            .all_tags()
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    Cell::from(Span::from(text))
+}
+
+// border of the pane that currently receives key presses
+fn pane_border(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    }
+}
+
+fn render_tree(rows: Vec<TreeRow>, focused: bool) -> List<'static> {
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| {
+            let marker = match (row.has_children, row.collapsed) {
+                (false, _) => "  ",
+                (true, true) => "▸ ",
+                (true, false) => "▾ ",
+            };
+            let indent = "  ".repeat(row.depth);
+            ListItem::new(format!("{}{}{}", indent, marker, row.label()))
+        })
+        .collect();
+
+    List::new(items)
+        .block(
+            Block::default()
+                .title("Notebooks")
+                .borders(Borders::ALL)
+                .border_style(pane_border(focused))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(Style::default().bg(Color::White).fg(Color::Black))
+}
+
+fn render_notes<'a>(
+    notes: Vec<&'a Note>,
+    columns: &[Column],
+    widths: &'a [Constraint],
+    focused: bool,
+) -> Table<'a> {
+    let rows: Vec<Row> = notes
+        .into_iter()
+        .map(|note| Row::new(columns.iter().map(|&column| column_cell(column, note))))
+        .collect();
+
+    let col_names = Row::new(
+        columns
+            .iter()
+            .map(|&column| Cell::from(column_header(column)).add_modifier(Modifier::BOLD)),
+    );
+
+    let table = Table::new(rows)
+        .header(col_names)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default())
+                .border_style(pane_border(focused))
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(Style::default().bg(Color::White).fg(Color::Black))
+        .widths(widths);
+
+    table
+}
+
+fn render_trash(app: &AppState) -> Table<'_> {
+    const WIDTHS: [Constraint; 3] = [
+        Constraint::Percentage(40),
+        Constraint::Percentage(30),
+        Constraint::Percentage(30),
+    ];
+
+    let rows: Vec<Row> = app
+        .trashed_notes()
+        .into_iter()
+        .filter_map(|id| app.notes.trashed().find(|note| note.id == id))
+        .map(|note| {
+            let deleted_at = note
+                .deleted_at
+                .map(|time| time.to_string())
+                .unwrap_or_default();
             Row::new(vec![
-                Cell::from(Span::from(id.to_string())),
                 Cell::from(Span::from(note.title.clone())),
-                Cell::from(Span::from(note.created_at.to_string())),
+                Cell::from(Span::from(note.notebook.clone())),
+                Cell::from(Span::from(deleted_at)),
             ])
         })
         .collect();
 
     let col_names = Row::new(vec![
-        Cell::from("ID").add_modifier(Modifier::BOLD),
         Cell::from("Title").add_modifier(Modifier::BOLD),
-        Cell::from("Created At").add_modifier(Modifier::BOLD),
+        Cell::from("Notebook").add_modifier(Modifier::BOLD),
+        Cell::from("Deleted At").add_modifier(Modifier::BOLD),
     ]);
 
-    let table = Table::new(rows.into_iter())
+    Table::new(rows)
         .header(col_names)
         .block(
             Block::default()
+                .title("Trash")
                 .borders(Borders::ALL)
-                .style(Style::default())
                 .border_type(BorderType::Plain),
         )
         .highlight_style(Style::default().bg(Color::White).fg(Color::Black))
-        .widths(&[
-            Constraint::Percentage(10),
-            Constraint::Percentage(40),
-            Constraint::Percentage(20),
-        ]);
+        .widths(&WIDTHS)
+}
 
-    table
+// graphemes of context shown before a match in the results snippet
+const SNIPPET_CONTEXT: usize = 30;
+
+// the matched line with the match highlighted, trimmed to start near the match
+fn snippet(text: &str, start: usize, end: usize) -> Line<'static> {
+    let skip = start.saturating_sub(SNIPPET_CONTEXT);
+    let chars: Vec<&str> = text.graphemes(true).collect();
+    let slice = |from: usize, to: usize| chars[from..to].concat();
+
+    let mut spans = Vec::new();
+    if skip > 0 {
+        spans.push(Span::raw("…"));
+    }
+    spans.push(Span::raw(slice(skip, start)));
+    spans.push(Span::styled(
+        slice(start, end),
+        Style::default().bg(Color::Yellow).fg(Color::Black),
+    ));
+    spans.push(Span::raw(slice(end, chars.len())));
+    Line::from(spans)
+}
+
+fn render_search(app: &AppState) -> Table<'_> {
+    const WIDTHS: [Constraint; 3] = [
+        Constraint::Percentage(25),
+        Constraint::Length(6),
+        Constraint::Percentage(70),
+    ];
+
+    let rows: Vec<Row> = app
+        .search_results
+        .iter()
+        .filter_map(|result| {
+            let note = app.notes.find(result.note)?;
+            let (line, text) = match result.line {
+                Some(row) => ((row + 1).to_string(), note.content.get(row)?),
+                None => ("title".to_string(), &note.title),
+            };
+            Some(Row::new(vec![
+                Cell::from(Span::from(note.title.clone())),
+                Cell::from(Span::from(line)),
+                Cell::from(snippet(text, result.start, result.end)),
+            ]))
+        })
+        .collect();
+
+    let col_names = Row::new(vec![
+        Cell::from("Note").add_modifier(Modifier::BOLD),
+        Cell::from("Line").add_modifier(Modifier::BOLD),
+        Cell::from("Match").add_modifier(Modifier::BOLD),
+    ]);
+
+    Table::new(rows)
+        .header(col_names)
+        .block(
+            Block::default()
+                .title(format!(
+                    "Search \"{}\" ({} matches)",
+                    app.search_query,
+                    app.search_results.len()
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&WIDTHS)
+}
+
+// open tasks grouped by note, the title only shown on a note's first task.
+// Overdue dates are red and today's yellow
+fn render_tasks(app: &AppState) -> Table<'_> {
+    const WIDTHS: [Constraint; 3] = [
+        Constraint::Percentage(25),
+        Constraint::Percentage(60),
+        Constraint::Length(10),
+    ];
+
+    let today = Local::now().date_naive();
+    let rows: Vec<Row> = app
+        .tasks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, task)| {
+            let note = app.notes.find(task.note)?;
+            let first = idx == 0 || app.tasks[idx - 1].note != task.note;
+            let title = match first {
+                true => note.title.clone(),
+                false => String::new(),
+            };
+            let due = match task.due {
+                Some(due) if due < today => {
+                    Span::styled(due.to_string(), Style::default().fg(Color::Red))
+                }
+                Some(due) if due == today => {
+                    Span::styled(due.to_string(), Style::default().fg(Color::Yellow))
+                }
+                Some(due) => Span::from(due.to_string()),
+                None => Span::from(""),
+            };
+            Some(Row::new(vec![
+                Cell::from(Span::styled(title, Style::default().bold())),
+                Cell::from(Span::from(task.text.clone())),
+                Cell::from(due),
+            ]))
+        })
+        .collect();
+
+    let col_names = Row::new(vec![
+        Cell::from("Note").add_modifier(Modifier::BOLD),
+        Cell::from("Task").add_modifier(Modifier::BOLD),
+        Cell::from("Due").add_modifier(Modifier::BOLD),
+    ]);
+
+    Table::new(rows)
+        .header(col_names)
+        .block(
+            Block::default()
+                .title(format!("Tasks ({} open)", app.tasks.len()))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&WIDTHS)
+}
+
+// the title with the fuzzy matched chars highlighted
+fn highlight_matches(title: &str, found: &FuzzyMatch) -> Line<'static> {
+    let spans: Vec<Span> = title
+        .chars()
+        .enumerate()
+        .map(|(i, ch)| match found.positions.contains(&i) {
+            true => Span::styled(ch.to_string(), Style::default().fg(Color::Yellow).bold()),
+            false => Span::raw(ch.to_string()),
+        })
+        .collect();
+    Line::from(spans)
+}
+
+fn render_quick_open(app: &AppState, quick_open: &QuickOpen, frame: &mut Frame, area: Rect) {
+    let results = app.quick_open_results();
+    let block = Block::default()
+        .title(format!("Open note ({} matches)", results.len()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Plain)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)])
+        .split(inner);
+
+    let input = Paragraph::new(Line::from(vec![
+        Span::styled("> ", Style::default().bold()),
+        Span::raw(quick_open.query.clone()),
+    ]))
+    .block(Block::default().borders(Borders::BOTTOM));
+    frame.render_widget(input, parts[0]);
+
+    let items: Vec<ListItem> = results
+        .iter()
+        .filter_map(|(id, found)| {
+            let note = app.notes.find(*id)?;
+            Some(ListItem::new(highlight_matches(&note.title, found)))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut idx = ListState::default();
+    idx.select((!results.is_empty()).then_some(quick_open.selected));
+    frame.render_stateful_widget(list, parts[1], &mut idx);
+}
+
+fn render_confirm(app: &AppState, confirm: Confirm) -> Paragraph<'_> {
+    let question = match confirm {
+        Confirm::Purge(id) => {
+            let title = app
+                .notes
+                .trashed()
+                .find(|note| note.id == id)
+                .map(|note| note.title.as_str())
+                .unwrap_or_default();
+            format!("Permanently delete \"{}\"?", title)
+        }
+        Confirm::EmptyTrash => format!(
+            "Permanently delete all {} notes in the trash?",
+            app.notes.trashed().count()
+        ),
+    };
+
+    Paragraph::new(vec![
+        Line::from(Span::styled(question, Style::default().bold())),
+        Line::from(""),
+        Line::from(format!(
+            "This can't be undone. {}",
+            key_hints(app, Scope::Confirm)
+        )),
+    ])
+    .block(
+        Block::default()
+            .title("Confirm")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain),
+    )
+    .wrap(Wrap { trim: false })
 }
 
 fn render_nav(app: &mut AppState) -> Paragraph<'_> {
-    let nav_hints = {
+    if let Some(find) = &app.find {
+        return render_find(app, find);
+    }
+    let nav_hints = if app.quick_open.is_some() {
+        Span::styled(key_hints(app, Scope::QuickOpen), Style::default().bold())
+    } else if let Some(prompt) = app.prompt {
+        Span::styled(
+            format!("{}: {}", prompt.label(), app.prompt_buf),
+            Style::default().bold(),
+        )
+    } else if let Some(status) = &app.status {
+        Span::styled(status.clone(), Style::default().bold())
+    } else if !app.pending_keys.is_empty() {
+        Span::styled(
+            format!("{} ...", keybindings::describe(&app.pending_keys)),
+            Style::default().bold(),
+        )
+    } else {
         match app.current_view {
             CurrentView::Main => {
-                let msg: String;
-                if app.editing_title {
-                    msg = format!("Enter title: {}", app.title_buf.to_string());
+                let msg = if app.sidebar_focus {
+                    key_hints(app, Scope::Notebooks)
                 } else {
-                    msg = "(q/Esc) to quit".to_string();
-                }
+                    let mut msg = key_hints(app, Scope::Notes);
+                    if let Some(filter) = &app.tag_filter {
+                        msg.push_str(&format!(
+                            " [filter: {}, ({}) AND/OR ({}) clear]",
+                            filter,
+                            action_keys(app, Scope::Notes, Action::ToggleFilterMode),
+                            action_keys(app, Scope::Notes, Action::Back)
+                        ));
+                    }
+                    if app.read_only {
+                        msg.push_str(" [read-only, changes won't be saved]");
+                    }
+                    msg
+                };
                 Span::styled(msg, Style::default().bold())
             }
 
-            CurrentView::Editing => Span::styled("(Esc) to quit", Style::default().bold()),
+            CurrentView::Editing => {
+                let hints = key_hints(app, Scope::Editor);
+                let msg = [
+                    app.textbox.mode_indicator(),
+                    Some(hints),
+                    app.textbox.keymap_hints(),
+                ]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+                Span::styled(msg, Style::default().bold())
+            }
+
+            CurrentView::Trash => {
+                Span::styled(key_hints(app, Scope::Trash), Style::default().bold())
+            }
+
+            CurrentView::Search => {
+                Span::styled(key_hints(app, Scope::Search), Style::default().bold())
+            }
+
+            CurrentView::Tasks => {
+                Span::styled(key_hints(app, Scope::Tasks), Style::default().bold())
+            }
         }
     };
 
     Paragraph::new(Line::from(nav_hints))
 }
+
+// the query and replacement being typed above the find bar's keys
+fn render_find<'a>(app: &'a AppState, find: &FindBar) -> Paragraph<'a> {
+    let field = |focused: bool| match focused {
+        true => Style::default().bold(),
+        false => Style::default(),
+    };
+    let cursor = (app.textbox.cursor.row, app.textbox.cursor.col);
+    let count = if find.invalid {
+        " [invalid regex]".to_string()
+    } else if find.regex.is_none() {
+        String::new()
+    } else if find.matches.is_empty() {
+        " [no matches]".to_string()
+    } else {
+        let current = find
+            .current(cursor)
+            .map_or("-".to_string(), |i| (i + 1).to_string());
+        format!(" [{}/{}]", current, find.matches.len())
+    };
+
+    let mut fields = vec![
+        Span::styled(format!("Find: {}", find.query), field(!find.replacing)),
+        Span::raw(count),
+    ];
+    if find.replacing || !find.replacement.is_empty() {
+        fields.push(Span::styled(
+            format!("  Replace: {}", find.replacement),
+            field(find.replacing),
+        ));
+    }
+
+    let keys = match &app.status {
+        Some(status) => status.clone(),
+        None if find.replacing => key_hints(app, Scope::Replace),
+        None => key_hints(app, Scope::Find),
+    };
+
+    Paragraph::new(vec![Line::from(fields), Line::from(keys)])
+}
+
+// hints for every labelled action bound in the scope, e.g. `(q/^C) quit (n) new`
+fn key_hints(app: &AppState, scope: Scope) -> String {
+    let flag = |on: bool| if on { "on" } else { "off" };
+    let search = app.search_options;
+    let find = app
+        .find
+        .as_ref()
+        .map(|find| find.options)
+        .unwrap_or_default();
+    Action::all()
+        .filter(|action| !action.label().is_empty())
+        .filter_map(|action| {
+            let keys = action_keys(app, scope, action);
+            if keys.is_empty() {
+                return None;
+            }
+            // the search and find bar options show whether they're on
+            let on = match action {
+                Action::ToggleCase => Some(search.case_sensitive),
+                Action::ToggleWholeWord => Some(search.whole_word),
+                Action::ToggleRegex => Some(search.regex),
+                Action::FindCase => Some(find.case_sensitive),
+                Action::FindWholeWord => Some(find.whole_word),
+                Action::FindRegex => Some(find.regex),
+                _ => None,
+            };
+            let label = match on {
+                Some(on) => format!("{}: {}", action.label(), flag(on)),
+                None => action.label().to_string(),
+            };
+            Some(format!("({}) {}", keys, label))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// keys bound to an action, leaving out those the editor's keymap takes first
+fn action_keys(app: &AppState, scope: Scope, action: Action) -> String {
+    let claimed = |key: Chord| {
+        scope == Scope::Editor
+            && app.textbox.claims_key(key.code, key.modifiers)
+            && !app.keybindings.is_custom(scope, key)
+    };
+    app.keybindings
+        .keys(scope, action)
+        .into_iter()
+        .filter(|keys| !claimed(keys[0]))
+        .map(keybindings::describe)
+        .collect::<Vec<_>>()
+        .join("/")
+}
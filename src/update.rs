@@ -1,115 +1,720 @@
+use anyhow::{Context, Result};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 use crate::{
-    app::{AppState, CurrentView},
+    app::{AppState, Confirm, CurrentView, Prompt, QuickOpen},
+    keybindings::{Action, Chord, Lookup, Scope},
     note::Note,
+    notebook,
+    tags::{self, TagFilter, TagMatch},
+    textbox::EditCommand,
 };
 
 pub fn update(app: &mut AppState, key_event: KeyEvent) {
-    match app.current_view {
-        CurrentView::Main => {
-            if !app.editing_title {
-                match key_event.code {
-                    // close the program
-                    KeyCode::Esc | KeyCode::Char('q') => app.quit(),
-
-                    KeyCode::Char('c') | KeyCode::Char('C') => {
-                        if key_event.modifiers == KeyModifiers::CONTROL {
-                            app.quit()
-                        }
-                    }
-                    // on home screen, create/open a note
-                    KeyCode::Char('n') => {
-                        let note = Note::new("", Vec::new());
-                        app.notes.insert(&note);
-                        app.current_note = app.notes.length() - 1;
-                        app.editing_title = true
-                    }
-
-                    KeyCode::Char('d') => {
-                        app.notes.remove(app.current_note);
-                        app.save().expect("Couldn't save notes");
-                    }
-
-                    KeyCode::Enter => {
-                        app.textbox.text = match app.notes.get(app.current_note) {
-                            Some(note) => note.clone().content,
-                            None => Vec::new(),
-                        };
-                        app.current_view = CurrentView::Editing
-                    }
-
-                    // navigate up/down list of notes
-                    KeyCode::Up => {
-                        if app.current_note == 0 {
-                            app.current_note = 0
-                        } else {
-                            app.current_note -= 1
-                        }
-                    }
-                    KeyCode::Down => {
-                        if app.current_note == app.notes.length() - 1 {
-                        } else {
-                            app.current_note += 1
-                        }
-                    }
-
-                    // default case
-                    _ => {}
+    app.status = None;
+    handle_key(app, key_event);
+    // text cut or copied by any keymap, e.g. Vim's `yy`, reaches the system clipboard
+    app.sync_clipboard();
+}
+
+fn handle_key(app: &mut AppState, key_event: KeyEvent) {
+    if app.recovery.is_some() {
+        update_recovery(app, key_event);
+        return;
+    }
+    if let Some(confirm) = app.confirm {
+        update_confirm(app, key_event, confirm);
+        return;
+    }
+    if app.quick_open.is_some() {
+        update_quick_open(app, key_event);
+        return;
+    }
+    if app.prompt.is_some() {
+        update_prompt(app, key_event);
+        return;
+    }
+    if app.find.is_some() {
+        update_find(app, key_event);
+        return;
+    }
+
+    let scope = match app.current_view {
+        CurrentView::Main if app.sidebar_focus => Scope::Notebooks,
+        CurrentView::Main => Scope::Notes,
+        CurrentView::Trash => Scope::Trash,
+        CurrentView::Search => Scope::Search,
+        CurrentView::Tasks => Scope::Tasks,
+        CurrentView::Editing => Scope::Editor,
+    };
+
+    // keys the editor's Vim or Emacs layer uses itself, e.g. Esc in insert
+    // mode, unless they're bound in the keybindings file
+    let claims = app.textbox.claims_key(key_event.code, key_event.modifiers)
+        && !app.keybindings.is_custom(scope, Chord::from(key_event));
+    let editor_claims = scope == Scope::Editor
+        && app.pending_keys.is_empty()
+        && (claims || (key_event.code == KeyCode::Esc && app.textbox.wants_esc()));
+    if editor_claims {
+        app.textbox
+            .handle_input(key_event.code, key_event.modifiers);
+        return;
+    }
+
+    match lookup_key(app, scope, key_event) {
+        Lookup::Action(action) => run_action(app, action),
+        Lookup::Unbound if scope == Scope::Editor => app
+            .textbox
+            .handle_input(key_event.code, key_event.modifiers),
+        _ => {}
+    }
+}
+
+// adds the key to the sequence typed so far. A key that isn't bound on its own
+// comes back as unbound so it can be typed, an unfinished sequence is dropped
+fn lookup_key(app: &mut AppState, scope: Scope, key_event: KeyEvent) -> Lookup {
+    app.pending_keys.push(Chord::from(key_event));
+    match app.keybindings.lookup(scope, &app.pending_keys) {
+        Lookup::Pending => Lookup::Pending,
+        Lookup::Action(action) => {
+            app.pending_keys.clear();
+            Lookup::Action(action)
+        }
+        Lookup::Unbound => {
+            let typed = app.pending_keys.len() == 1;
+            app.pending_keys.clear();
+            match typed {
+                true => Lookup::Unbound,
+                false => Lookup::Pending,
+            }
+        }
+    }
+}
+
+fn run_action(app: &mut AppState, action: Action) {
+    match action {
+        // the editor is saved before leaving it, and stays open if that failed
+        Action::Quit => {
+            let saved = match app.current_view {
+                CurrentView::Editing => app.save_editor(),
+                _ => true,
+            };
+            if saved {
+                app.quit()
+            }
+        }
+        Action::Back => match app.current_view {
+            CurrentView::Main if app.sidebar_focus => app.sidebar_focus = false,
+            // clear an active tag filter before quitting
+            CurrentView::Main if app.tag_filter.is_some() => app.set_tag_filter(None),
+            CurrentView::Main => app.quit(),
+            CurrentView::Trash => {
+                app.ensure_visible_selection();
+                app.current_view = CurrentView::Main
+            }
+            CurrentView::Search | CurrentView::Tasks => app.current_view = CurrentView::Main,
+            CurrentView::Editing => {
+                if app.save_editor() {
+                    app.textbox.reset();
+                    app.current_view = CurrentView::Main
                 }
             }
-            // else, editing title -> switch focus to nav
-            else {
-                match key_event.code {
-                    KeyCode::Char(ch) => {
-                        app.title_buf.push(ch);
-                    }
-                    KeyCode::Backspace => {
-                        app.title_buf.pop();
-                    }
-                    KeyCode::Esc => {
-                        app.editing_title = false;
-                        app.title_buf.clear();
-                    }
-                    KeyCode::Enter => {
-                        let note = app.notes.get(app.current_note).expect("Couldn't open note.");
-                        note.set_title(&app.title_buf);
-                        app.editing_title = false;
-                        app.current_view = CurrentView::Editing;
-                        app.textbox.text = match app.notes.get(app.current_note) {
-                            Some(note) => note.clone().content,
-                            None => Vec::new(),
-                        };
-                    }
-                    _ => {}
+        },
+        Action::QuickOpen => app.quick_open = Some(QuickOpen::default()),
+        Action::Search => {
+            let query = app.search_query.clone();
+            app.open_prompt(Prompt::Search, query)
+        }
+        Action::FocusNotebooks => app.sidebar_focus = true,
+        Action::FocusNotes => app.sidebar_focus = false,
+
+        // on home screen, create/open a note
+        Action::NewNote => {
+            let mut note = Note::new("", Vec::new());
+            note.notebook = app.target_notebook();
+            app.notes.insert(&note);
+            app.current_note = Some(note.id);
+            app.open_prompt(Prompt::Title, String::new())
+        }
+        Action::OpenNote => app.open_current_note(),
+        Action::EditTags => {
+            if let Some(note) = app.current_note.and_then(|id| app.notes.find(id)) {
+                let tags = note.tags.join(" ");
+                app.open_prompt(Prompt::Tags, tags)
+            }
+        }
+        // filter the table by tags, the filter mode switches between AND/OR
+        Action::FilterTags => {
+            let tags = match &app.tag_filter {
+                Some(filter) => filter.tags.join(" "),
+                None => String::new(),
+            };
+            app.open_prompt(Prompt::TagFilter, tags)
+        }
+        Action::ToggleFilterMode => {
+            if let Some(filter) = app.tag_filter.as_mut() {
+                filter.toggle_mode();
+            }
+            app.ensure_visible_selection();
+        }
+        // move the selected note to another notebook
+        Action::MoveNote => {
+            if let Some(note) = app.current_note.and_then(|id| app.notes.find(id)) {
+                let path = note.notebook.clone();
+                app.open_prompt(Prompt::MoveNote, path)
+            }
+        }
+        Action::DeleteNote => {
+            app.trash_current_note();
+            app.try_save();
+        }
+        Action::ShowTrash => app.open_trash(),
+        Action::ShowTasks => app.open_tasks(),
+        Action::PrevNote => app.select_prev(),
+        Action::NextNote => app.select_next(),
+        Action::FirstNote => app.select_first(),
+        Action::LastNote => app.select_last(),
+
+        Action::NewNotebook => {
+            let prefix = match &app.current_notebook {
+                Some(path) => format!("{}/", path),
+                None => String::new(),
+            };
+            app.open_prompt(Prompt::NewNotebook, prefix)
+        }
+        Action::RenameNotebook => {
+            if let Some(path) = &app.current_notebook {
+                let name = notebook::name(path).to_string();
+                app.open_prompt(Prompt::RenameNotebook, name)
+            }
+        }
+        Action::MoveNotebook => {
+            if let Some(path) = &app.current_notebook {
+                let parent = notebook::parent(path).unwrap_or_default().to_string();
+                app.open_prompt(Prompt::MoveNotebook, parent)
+            }
+        }
+        Action::ToggleNotebook => app.toggle_collapsed(),
+        // collapse, or jump to the parent if already collapsed
+        Action::CollapseNotebook => match app.current_notebook.clone() {
+            Some(path) if !app.collapsed.contains(&path) && has_children(app, &path) => {
+                app.set_collapsed(true)
+            }
+            Some(path) => {
+                app.select_notebook(notebook::parent(&path).map(String::from));
+            }
+            None => {}
+        },
+        Action::ExpandNotebook => app.set_collapsed(false),
+        Action::PrevNotebook => app.select_prev_notebook(),
+        Action::NextNotebook => app.select_next_notebook(),
+
+        Action::RestoreTrashed => {
+            app.restore_current_trashed();
+            app.try_save();
+        }
+        Action::PurgeTrashed => {
+            if let Some(id) = app.current_trashed {
+                app.confirm = Some(Confirm::Purge(id))
+            }
+        }
+        Action::EmptyTrash => {
+            if app.notes.trashed().next().is_some() {
+                app.confirm = Some(Confirm::EmptyTrash)
+            }
+        }
+        Action::PrevTrashed => app.select_prev_trashed(),
+        Action::NextTrashed => app.select_next_trashed(),
+
+        Action::OpenResult => app.open_search_result(),
+        // toggle case sensitivity, whole word and regex matching
+        Action::ToggleCase | Action::ToggleWholeWord | Action::ToggleRegex => {
+            let options = &mut app.search_options;
+            match action {
+                Action::ToggleCase => options.case_sensitive = !options.case_sensitive,
+                Action::ToggleWholeWord => options.whole_word = !options.whole_word,
+                _ => options.regex = !options.regex,
+            }
+            if let Err(e) = app.run_search() {
+                app.search_results.clear();
+                app.status = Some(e.to_string());
+            }
+        }
+        Action::PrevResult => app.search_selected = app.search_selected.saturating_sub(1),
+        Action::NextResult => {
+            let last = app.search_results.len().saturating_sub(1);
+            app.search_selected = (app.search_selected + 1).min(last)
+        }
+
+        Action::OpenTask => app.open_task(),
+        Action::CompleteTask => {
+            if let Err(e) = app.complete_task() {
+                app.status = Some(e.to_string());
+            }
+        }
+        Action::PrevTask => app.task_selected = app.task_selected.saturating_sub(1),
+        Action::NextTask => {
+            let last = app.tasks.len().saturating_sub(1);
+            app.task_selected = (app.task_selected + 1).min(last)
+        }
+
+        Action::Save => app.set_current_note(),
+        Action::Undo => app.textbox.command(EditCommand::Undo),
+        Action::Redo => app.textbox.command(EditCommand::Redo),
+        Action::Copy if app.textbox.selection().is_none() => {}
+        Action::Copy => app.textbox.command(EditCommand::Copy),
+        Action::Cut => app.textbox.command(EditCommand::Cut),
+        Action::Paste => {
+            app.paste_from_clipboard();
+            app.textbox.command(EditCommand::Paste)
+        }
+        Action::Find => app.open_find(),
+        Action::Preview => app.toggle_preview(),
+        Action::PreviewUp => app.scroll_preview(false),
+        Action::PreviewDown => app.scroll_preview(true),
+        Action::ToggleTask => app.textbox.command(EditCommand::ToggleTask),
+
+        // only bound in the find bar, quick open and the dialogs, handled there
+        Action::FindNext
+        | Action::FindPrev
+        | Action::Replace
+        | Action::ReplaceAll
+        | Action::FocusReplace
+        | Action::FocusFind
+        | Action::FindCase
+        | Action::FindWholeWord
+        | Action::FindRegex
+        | Action::OpenMatch
+        | Action::PrevMatch
+        | Action::NextMatch
+        | Action::Submit
+        | Action::Close
+        | Action::ConfirmYes
+        | Action::ConfirmNo
+        | Action::RestoreBackup
+        | Action::StartEmpty
+        | Action::ToggleDetails => {}
+    }
+}
+
+// pasted text goes to whatever is taking input, the editor gets it as one edit
+pub fn paste(app: &mut AppState, text: &str) {
+    if app.recovery.is_some() || app.confirm.is_some() {
+        return;
+    }
+    let single_line = || text.lines().collect::<Vec<_>>().join(" ");
+    if let Some(quick_open) = app.quick_open.as_mut() {
+        quick_open.query.push_str(&single_line());
+        quick_open.selected = 0;
+    } else if app.prompt.is_some() {
+        app.prompt_buf.push_str(&single_line());
+    } else if let Some(find) = app.find.as_mut() {
+        match find.replacing {
+            true => find.replacement.push_str(&single_line()),
+            false => {
+                find.query.push_str(&single_line());
+                app.run_find()
+            }
+        }
+    } else if let CurrentView::Editing = app.current_view {
+        app.textbox.paste_text(text);
+    }
+}
+
+fn update_quick_open(app: &mut AppState, key_event: KeyEvent) {
+    let results = app.quick_open_results().len();
+    let lookup = lookup_key(app, Scope::QuickOpen, key_event);
+    let Some(quick_open) = app.quick_open.as_mut() else {
+        return;
+    };
+    match lookup {
+        Lookup::Action(Action::Close) => app.quick_open = None,
+        Lookup::Action(Action::PrevMatch) => {
+            quick_open.selected = quick_open.selected.saturating_sub(1)
+        }
+        Lookup::Action(Action::NextMatch) => {
+            quick_open.selected = (quick_open.selected + 1).min(results.saturating_sub(1))
+        }
+        Lookup::Action(Action::OpenMatch) => app.open_quick_open_selection(),
+        Lookup::Unbound if is_typed(key_event) => {
+            match key_event.code {
+                KeyCode::Char(ch) => quick_open.query.push(ch),
+                _ => {
+                    quick_open.query.pop();
                 }
             }
+            quick_open.selected = 0
         }
-        CurrentView::Editing => match key_event.code {
-            KeyCode::Esc => {
-                let _ = app.save();
-                app.textbox.reset();
-                app.current_view = CurrentView::Main
+        _ => {}
+    }
+}
+
+// characters and Backspace edit the text of a prompt or bar
+fn is_typed(key_event: KeyEvent) -> bool {
+    let modified = key_event
+        .modifiers
+        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+    matches!(key_event.code, KeyCode::Char(_) | KeyCode::Backspace) && !modified
+}
+
+// keys of the find bar, typing edits the query or the replacement
+fn update_find(app: &mut AppState, key_event: KeyEvent) {
+    let scope = match app.find.as_ref().map(|find| find.replacing) {
+        Some(true) => Scope::Replace,
+        Some(false) => Scope::Find,
+        None => return,
+    };
+    let lookup = lookup_key(app, scope, key_event);
+    let Some(find) = app.find.as_mut() else {
+        return;
+    };
+    let options = &mut find.options;
+    match lookup {
+        Lookup::Action(Action::Close) => app.close_find(),
+        // toggle case sensitivity, whole word and regex matching
+        Lookup::Action(Action::FindCase) => {
+            options.case_sensitive = !options.case_sensitive;
+            app.run_find()
+        }
+        Lookup::Action(Action::FindWholeWord) => {
+            options.whole_word = !options.whole_word;
+            app.run_find()
+        }
+        Lookup::Action(Action::FindRegex) => {
+            options.regex = !options.regex;
+            app.run_find()
+        }
+        Lookup::Action(Action::ReplaceAll) => app.replace_all(),
+        Lookup::Action(Action::FocusReplace | Action::FocusFind) => {
+            find.replacing = !find.replacing
+        }
+        Lookup::Action(Action::Replace) => app.replace_next(),
+        Lookup::Action(Action::FindNext) => app.find_next(true),
+        Lookup::Action(Action::FindPrev) => app.find_next(false),
+        Lookup::Unbound if is_typed(key_event) => {
+            let text = match find.replacing {
+                true => &mut find.replacement,
+                false => &mut find.query,
+            };
+            match key_event.code {
+                KeyCode::Char(ch) => text.push(ch),
+                _ => {
+                    text.pop();
+                }
+            }
+            if !find.replacing {
+                app.run_find()
             }
-            KeyCode::Char(value) => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    match value {
-                        's' | 'S' => app.set_current_note(),
-                        'c' | 'C' => {
-                            let _ = app.save();
-                            app.quit()
-                        }
-                        _ => {}
-                    }
-                } else {
-                    app.textbox
-                        .handle_input(key_event.code, key_event.modifiers)
+        }
+        _ => {}
+    }
+}
+
+fn update_confirm(app: &mut AppState, key_event: KeyEvent, confirm: Confirm) {
+    match lookup_key(app, Scope::Confirm, key_event) {
+        Lookup::Action(Action::ConfirmYes) => {
+            match confirm {
+                Confirm::Purge(id) => {
+                    app.purge_trashed(id);
+                }
+                Confirm::EmptyTrash => {
+                    app.notes.empty_trash();
+                    app.current_trashed = None;
                 }
             }
+            app.confirm = None;
+            app.try_save();
+        }
+        Lookup::Action(Action::ConfirmNo) => app.confirm = None,
+        _ => {}
+    }
+}
+
+fn has_children(app: &AppState, path: &str) -> bool {
+    app.notes
+        .notebooks()
+        .iter()
+        .any(|other| other != path && notebook::is_within(other, path))
+}
+
+fn update_prompt(app: &mut AppState, key_event: KeyEvent) {
+    match lookup_key(app, Scope::Prompt, key_event) {
+        Lookup::Action(Action::Close) => app.close_prompt(),
+        Lookup::Action(Action::Submit) => {
+            if let Some(prompt) = app.prompt {
+                let input = std::mem::take(&mut app.prompt_buf);
+                app.close_prompt();
+                if let Err(e) = submit_prompt(app, prompt, &input) {
+                    app.status = Some(e.to_string());
+                }
+            }
+        }
+        Lookup::Unbound if is_typed(key_event) => match key_event.code {
+            KeyCode::Char(ch) => app.prompt_buf.push(ch),
             _ => {
-                app.textbox
-                    .handle_input(key_event.code, key_event.modifiers);
+                app.prompt_buf.pop();
             }
         },
+        _ => {}
+    }
+}
+
+fn submit_prompt(app: &mut AppState, prompt: Prompt, input: &str) -> Result<()> {
+    match prompt {
+        Prompt::Title => {
+            if let Some(note) = app.current_note.and_then(|id| app.notes.get(id)) {
+                note.set_title(input);
+            }
+            app.open_current_note();
+        }
+        Prompt::Tags => {
+            let (tags, invalid) = tags::parse_tags(input);
+            if let Some(note) = app.current_note.and_then(|id| app.notes.get(id)) {
+                note.set_tags(tags);
+            }
+            app.ensure_visible_selection();
+            app.save()?;
+            report_invalid_tags(&invalid)?;
+        }
+        Prompt::TagFilter => {
+            let (tags, invalid) = tags::parse_tags(input);
+            let mode = match &app.tag_filter {
+                Some(filter) => filter.mode,
+                None => TagMatch::All,
+            };
+            let filter = (!tags.is_empty()).then(|| TagFilter::new(tags, mode));
+            app.set_tag_filter(filter);
+            report_invalid_tags(&invalid)?;
+        }
+        Prompt::NewNotebook => {
+            let path = notebook::normalize(input).context("Notebook name can't be empty")?;
+            app.notes.add_notebook(&path);
+            app.select_notebook(Some(path));
+            app.save()?;
+        }
+        Prompt::RenameNotebook => {
+            let name = notebook::normalize(input).context("Notebook name can't be empty")?;
+            if name.contains('/') {
+                anyhow::bail!("Notebook names can't contain /, use (m) to move it");
+            }
+            let from = app.current_notebook.clone().unwrap_or_default();
+            let to = match notebook::parent(&from) {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name,
+            };
+            app.move_current_notebook(&to)?;
+            app.save()?;
+        }
+        Prompt::MoveNotebook => {
+            let from = app.current_notebook.clone().unwrap_or_default();
+            let name = notebook::name(&from);
+            let to = match notebook::normalize(input) {
+                Some(parent) => format!("{}/{}", parent, name),
+                None => name.to_string(),
+            };
+            app.move_current_notebook(&to)?;
+            app.save()?;
+        }
+        Prompt::MoveNote => {
+            let path = notebook::normalize(input)
+                .unwrap_or_else(|| notebook::DEFAULT_NOTEBOOK.to_string());
+            if let Some(id) = app.current_note {
+                app.notes.move_note(id, &path);
+            }
+            app.ensure_visible_selection();
+            app.save()?;
+        }
+        Prompt::Search => {
+            app.search_query = input.to_string();
+            app.run_search()?;
+        }
+    }
+    Ok(())
+}
+
+// the valid tags are used, the others shown in the nav bar
+fn report_invalid_tags(invalid: &[String]) -> Result<()> {
+    if !invalid.is_empty() {
+        anyhow::bail!(
+            "Ignored {}, tags can only have letters, digits, -, _ and /",
+            invalid.join(" ")
+        );
+    }
+    Ok(())
+}
+
+fn update_recovery(app: &mut AppState, key_event: KeyEvent) {
+    match lookup_key(app, Scope::Recovery, key_event) {
+        Lookup::Action(Action::RestoreBackup) => app.restore_backup(),
+        Lookup::Action(Action::StartEmpty) => app.start_empty(),
+        Lookup::Action(Action::ToggleDetails) => {
+            if let Some(recovery) = app.recovery.as_mut() {
+                recovery.show_details = !recovery.show_details
+            }
+        }
+        Lookup::Action(Action::Quit) => app.quit(),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, EditingMode},
+        keybindings::Keybindings,
+        note::NoteList,
+    };
+    use tempfile::TempDir;
+
+    fn new_app(config: Config) -> (TempDir, AppState) {
+        let dir = tempfile::tempdir().unwrap();
+        let app = AppState::new(20, dir.path().join("notes.json"), config);
+        (dir, app)
+    }
+
+    fn press(app: &mut AppState, code: KeyCode, modifiers: KeyModifiers) {
+        update(app, KeyEvent::new(code, modifiers))
+    }
+
+    fn type_str(app: &mut AppState, text: &str) {
+        for ch in text.chars() {
+            press(app, KeyCode::Char(ch), KeyModifiers::NONE)
+        }
+    }
+
+    // create a note and open it in the editor
+    fn new_note(app: &mut AppState) {
+        type_str(app, "ntitle");
+        press(app, KeyCode::Enter, KeyModifiers::NONE);
+        assert!(matches!(app.current_view, CurrentView::Editing));
+    }
+
+    #[test]
+    fn test_leaving_the_editor_saves_it() {
+        let (_dir, mut app) = new_app(Config::default());
+        new_note(&mut app);
+        type_str(&mut app, "hello");
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+
+        assert!(matches!(app.current_view, CurrentView::Main));
+        let saved = NoteList::load(&app.save_file).unwrap();
+        assert_eq!(saved.iter().next().unwrap().content, vec!["hello"]);
+    }
+
+    #[test]
+    fn test_failed_save_keeps_the_editor_open() {
+        let (dir, mut app) = new_app(Config::default());
+        app.save_file = dir.path().join("missing").join("notes.json");
+        new_note(&mut app);
+        type_str(&mut app, "hello");
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+
+        assert!(matches!(app.current_view, CurrentView::Editing));
+        assert!(app.status.is_some());
+        assert_eq!(app.textbox.text.to_lines(), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_ctrl_c_doesnt_quit_the_editor() {
+        let (_dir, mut app) = new_app(Config::default());
+        new_note(&mut app);
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(!app.should_quit);
+
+        let (_dir, mut app) = new_app(Config {
+            editing_mode: EditingMode::Vim,
+            ..Config::default()
+        });
+        new_note(&mut app);
+        type_str(&mut app, "ihi");
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(!app.should_quit);
+        assert_eq!(app.textbox.mode_indicator().unwrap(), "-- NORMAL --");
+        assert_eq!(app.textbox.text.to_lines(), vec!["hi"]);
+    }
+
+    #[test]
+    fn test_copies_reach_the_clipboard() {
+        let (_dir, mut app) = new_app(Config::default());
+        new_note(&mut app);
+        type_str(&mut app, "hello");
+        press(&mut app, KeyCode::Left, KeyModifiers::SHIFT);
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(app.clipboard.take().as_deref(), Some("o"));
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::NONE);
+        assert_eq!(app.clipboard, None);
+    }
+
+    #[test]
+    fn test_keymap_copies_reach_the_clipboard() {
+        let (_dir, mut app) = new_app(Config {
+            editing_mode: EditingMode::Vim,
+            ..Config::default()
+        });
+        new_note(&mut app);
+        type_str(&mut app, "ihello");
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        type_str(&mut app, "yy");
+        assert_eq!(app.clipboard.take().as_deref(), Some("hello"));
+        type_str(&mut app, "l");
+        assert_eq!(app.clipboard, None);
+
+        let (_dir, mut app) = new_app(Config {
+            editing_mode: EditingMode::Emacs,
+            ..Config::default()
+        });
+        new_note(&mut app);
+        type_str(&mut app, "hello");
+        press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut app, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(app.clipboard.as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_file_bindings_win_over_emacs_keys() {
+        let (_dir, mut app) = new_app(Config {
+            editing_mode: EditingMode::Emacs,
+            ..Config::default()
+        });
+        app.keybindings =
+            Keybindings::from_toml("[editor]\n\"ctrl-k ctrl-s\" = \"editor.save\"\n").unwrap();
+        new_note(&mut app);
+        type_str(&mut app, "hello");
+        press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut app, KeyCode::Char('k'), KeyModifiers::CONTROL);
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+
+        assert_eq!(app.textbox.text.to_lines(), vec!["hello"]);
+        assert_eq!(app.notes.iter().next().unwrap().content, vec!["hello"]);
+    }
+
+    #[test]
+    fn test_prompt_keys() {
+        let (_dir, mut app) = new_app(Config::default());
+        type_str(&mut app, "ntitle");
+        press(&mut app, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        press(&mut app, KeyCode::Backspace, KeyModifiers::ALT);
+        assert_eq!(app.prompt_buf, "title");
+        press(&mut app, KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(app.prompt.is_none());
+
+        app.keybindings = Keybindings::from_toml("[prompt]\ntab = \"prompt.submit\"\n").unwrap();
+        new_note(&mut app);
+        let note = app.current_note.and_then(|id| app.notes.find(id)).unwrap();
+        assert_eq!(note.title, "title");
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        type_str(&mut app, "t");
+        press(&mut app, KeyCode::Tab, KeyModifiers::NONE);
+        assert!(app.prompt.is_none());
+    }
+
+    #[test]
+    fn test_quit_after_starting_empty() {
+        let (_dir, mut app) = new_app(Config::default());
+        app.start_empty();
+        new_note(&mut app);
+        press(&mut app, KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert!(app.should_quit);
     }
 }